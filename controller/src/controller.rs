@@ -1,5 +1,8 @@
 use dust_dds::publication::data_writer::DataWriter;
-use types::{Color, DobotPose, MotorSpeed, Suction};
+use types::{
+    pose_library::PoseLibrary, Color, DobotMotionProfile, DobotMoveCommand, DobotPose,
+    MotionProfile, MotorSpeed, MoveType, Suction,
+};
 
 
 
@@ -7,61 +10,117 @@ pub const CONVEYOR_BELT_SPEED: MotorSpeed = MotorSpeed { speed: 7500 };
 
 const TOLERANCE: f32 = 0.5;
 
+/// How far the arc to a bin bulges outwards and upwards, in mm.
+const SWING_OFFSET: f32 = 20.0;
+const SWING_HEIGHT: f32 = 25.0;
+/// Arcs whose midpoint is closer than this to the base axis, in mm, are not swung.
+const MIN_SWING_RADIUS: f32 = 1.0;
+
+// The poses refer to the tool center point, so they depend on the tool offset
+// the `dobot` node is started with. Poses taught into the pose library replace them.
 const INITIAL_POSITION: DobotPose = DobotPose {
     x: 165.0,
     y: -5.0,
     z: 30.0,
     r: 0.0,
+    l: 0.0,
 };
 const ABOVE_BLOCK_POSITION: DobotPose = DobotPose {
     x: 251.0,
     y: -123.0,
     z: 25.0,
     r: 0.0,
+    l: 0.0,
 };
 const BLOCK_PICKUP_POSITION: DobotPose = DobotPose {
     x: 251.0,
     y: -123.0,
     z: 0.0,
     r: 0.0,
+    l: 0.0,
 };
 const COLOR_SENSOR_POSITION: DobotPose = DobotPose {
     x: 171.0,
     y: 59.0,
     z: 26.0,
     r: -8.0,
+    l: 0.0,
 };
 const ABOVE_COLOR_SENSOR_POSITION: DobotPose = DobotPose {
     x: 171.0,
     y: 59.0,
     z: 56.0,
     r: -8.0,
+    l: 0.0,
 };
 const BLOCK_DISPOSE_RED: DobotPose = DobotPose {
     x: 150.0,
     y: 162.0,
     z: 30.0,
     r: -15.0,
+    l: 0.0,
 };
 const BLOCK_DISPOSE_GREEN: DobotPose = DobotPose {
     x: 103.0,
     y: 173.0,
     z: 30.0,
     r: -15.0,
+    l: 0.0,
 };
 const BLOCK_DISPOSE_BLUE: DobotPose = DobotPose {
     x: 63.0,
     y: 175.0,
     z: 30.0,
     r: -15.0,
+    l: 0.0,
 };
 const BLOCK_DISPOSE_MIXED: DobotPose = DobotPose {
     x: 20.0,
     y: 177.0,
     z: 33.0,
     r: -10.0,
+    l: 0.0,
 };
 
+/// Poses of the cell the controller moves the arm to.
+#[derive(Debug, Clone, Copy)]
+pub struct Poses {
+    pub initial: DobotPose,
+    pub above_block: DobotPose,
+    pub block_pickup: DobotPose,
+    pub color_sensor: DobotPose,
+    pub above_color_sensor: DobotPose,
+    pub dispose_red: DobotPose,
+    pub dispose_green: DobotPose,
+    pub dispose_blue: DobotPose,
+    pub dispose_mixed: DobotPose,
+}
+
+impl Poses {
+    /// Take the poses by their field names from the library, the built-in
+    /// poses stay in place of the missing ones.
+    pub fn from_library(library: &PoseLibrary) -> Self {
+        let pose = |name, default| library.get(name).unwrap_or(default);
+        Self {
+            initial: pose("initial", INITIAL_POSITION),
+            above_block: pose("above_block", ABOVE_BLOCK_POSITION),
+            block_pickup: pose("block_pickup", BLOCK_PICKUP_POSITION),
+            color_sensor: pose("color_sensor", COLOR_SENSOR_POSITION),
+            above_color_sensor: pose("above_color_sensor", ABOVE_COLOR_SENSOR_POSITION),
+            dispose_red: pose("dispose_red", BLOCK_DISPOSE_RED),
+            dispose_green: pose("dispose_green", BLOCK_DISPOSE_GREEN),
+            dispose_blue: pose("dispose_blue", BLOCK_DISPOSE_BLUE),
+            dispose_mixed: pose("dispose_mixed", BLOCK_DISPOSE_MIXED),
+        }
+    }
+}
+
+impl Default for Poses {
+    fn default() -> Self {
+        Self::from_library(&PoseLibrary::default())
+    }
+}
+
 #[derive(Debug)]
 pub enum State {
     Initial,
@@ -76,12 +135,19 @@ pub enum State {
     MoveToBlue,
     MoveToMixed,
     DropBlock,
+    /// The arm lost steps, its moves were aborted and it is homed again.
+    ///
+    /// `is_started` is set once the `dobot` node reports homing, the homing
+    /// state published before belongs to an earlier homing.
+    Rehoming { is_started: bool },
 }
 
 pub struct Controller {
     pub conveyor_belt_writer: DataWriter<MotorSpeed>,
-    pub pose_writer: DataWriter<DobotPose>,
+    pub move_writer: DataWriter<DobotMoveCommand>,
     pub suction_writer: DataWriter<Suction>,
+    pub motion_profile_writer: DataWriter<DobotMotionProfile>,
+    poses: Poses,
     destination: DobotPose,
     pub state: State,
     pub time: std::time::Instant,
@@ -89,20 +155,48 @@ pub struct Controller {
 }
 
 fn distance(p1: DobotPose, p2: DobotPose) -> f32 {
-    ((p1.x - p2.x).powf(2.0) + (p1.y - p2.y).powf(2.0) + (p1.z - p2.z).powf(2.0)).sqrt()
+    ((p1.x - p2.x).powf(2.0)
+        + (p1.y - p2.y).powf(2.0)
+        + (p1.z - p2.z).powf(2.0)
+        + (p1.l - p2.l).powf(2.0))
+    .sqrt()
+}
+
+/// Point an arc from `from` to `to` passes through, lifted and pushed away from the base.
+///
+/// `None` if the midpoint is on the base axis, which gives no direction to push it.
+fn swing_via(from: DobotPose, to: DobotPose) -> Option<DobotPose> {
+    let x = (from.x + to.x) / 2.0;
+    let y = (from.y + to.y) / 2.0;
+    let radius = x.hypot(y);
+    if radius < MIN_SWING_RADIUS {
+        return None;
+    }
+    let scale = (radius + SWING_OFFSET) / radius;
+    Some(DobotPose {
+        x: x * scale,
+        y: y * scale,
+        z: from.z.max(to.z) + SWING_HEIGHT,
+        r: (from.r + to.r) / 2.0,
+        l: (from.l + to.l) / 2.0,
+    })
 }
 
 impl Controller {
     pub fn new(
         conveyor_belt_writer: DataWriter<MotorSpeed>,
-        pose_writer: DataWriter<DobotPose>,
+        move_writer: DataWriter<DobotMoveCommand>,
         suction_writer: DataWriter<Suction>,
+        motion_profile_writer: DataWriter<DobotMotionProfile>,
+        poses: Poses,
     ) -> Self {
         let mut controller = Self {
             conveyor_belt_writer,
-            pose_writer,
+            move_writer,
             suction_writer,
-            destination: INITIAL_POSITION,
+            motion_profile_writer,
+            poses,
+            destination: poses.initial,
             state: State::Initial,
             time: std::time::Instant::now(),
             color: Color { red: 0, green: 0, blue: 0 },
@@ -117,86 +211,120 @@ impl Controller {
         })
     }
 
+    /// Switch the profile of the moves which follow.
+    fn switch_profile(&mut self, profile: MotionProfile) {
+        self.motion_profile_writer
+            .write(&DobotMotionProfile::new(profile), None)
+            .unwrap();
+    }
+
+    fn move_to(&mut self, move_type: MoveType, destination: DobotPose) {
+        self.destination = destination;
+        self.move_writer
+            .write(&DobotMoveCommand::new(move_type, destination), None)
+            .unwrap();
+    }
+
     pub fn initial(&mut self) {
         self.state = State::Initial;
-        self.destination = INITIAL_POSITION;
         self.conveyor_belt_writer
             .write(&MotorSpeed { speed: 0 }, None)
             .unwrap();
         self.suction_writer
             .write(&Suction { is_on: false }, None)
             .unwrap();
-        self.pose_writer.write(&self.destination, None).unwrap();
+        self.switch_profile(MotionProfile::Fast);
+        self.move_to(MoveType::MoveJXyz, self.poses.initial);
     }
 
     pub fn get_ready(&mut self) {
         self.state = State::GetReady;
-        self.destination = ABOVE_BLOCK_POSITION;
-        self.pose_writer.write(&self.destination, None).unwrap();
+        self.move_to(MoveType::JumpXyz, self.poses.above_block);
     }
 
     pub fn wait_for_block(&mut self) {
         self.state = State::WaitForBlock;
-        self.destination = ABOVE_BLOCK_POSITION;
         self.conveyor_belt_writer
             .write(&CONVEYOR_BELT_SPEED, None)
             .unwrap();
-        self.pose_writer.write(&self.destination, None).unwrap();
+        self.move_to(MoveType::MoveJXyz, self.poses.above_block);
     }
 
     pub fn pick_up_block(&mut self) {
         self.state = State::PickUpBlock;
-        self.destination = BLOCK_PICKUP_POSITION;
         self.conveyor_belt_writer
             .write(&MotorSpeed { speed: 0 }, None)
             .unwrap();
         self.suction_writer
             .write(&Suction { is_on: true }, None)
             .unwrap();
-        self.pose_writer.write(&self.destination, None).unwrap();
+        self.move_to(MoveType::MoveLXyz, self.poses.block_pickup);
     }
 
     pub fn lift_up_block(&mut self) {
         self.state = State::LiftUpBlock;
-        self.destination = ABOVE_BLOCK_POSITION;
-        self.pose_writer.write(&self.destination, None).unwrap();
+        self.switch_profile(MotionProfile::Carry);
+        self.move_to(MoveType::MoveLXyz, self.poses.above_block);
     }
 
     fn move_block_to(&mut self, state: State, destination: DobotPose) {
         self.state = state;
-        self.destination = destination;
 
         self.conveyor_belt_writer
             .write(&MotorSpeed { speed: 0 }, None)
             .unwrap();
-        self.pose_writer.write(&self.destination, None).unwrap();
+        self.move_to(MoveType::JumpXyz, destination);
+    }
+
+    /// Swing the lifted block to a bin in one arc, or jump there if there is no arc to swing.
+    fn swing_block_to(&mut self, state: State, destination: DobotPose) {
+        let Some(via) = swing_via(self.destination, destination) else {
+            self.move_block_to(state, destination);
+            return;
+        };
+        self.state = state;
+
+        self.conveyor_belt_writer
+            .write(&MotorSpeed { speed: 0 }, None)
+            .unwrap();
+        self.destination = destination;
+        self.move_writer
+            .write(&DobotMoveCommand::arc(via, destination), None)
+            .unwrap();
     }
 
     pub fn check_color(&mut self) {
         self.time = std::time::Instant::now();
-        self.move_block_to(State::CheckColor, COLOR_SENSOR_POSITION);
+        self.move_block_to(State::CheckColor, self.poses.color_sensor);
     }
 
     pub fn lift_up_from_color(&mut self) {
-        self.destination = ABOVE_COLOR_SENSOR_POSITION;
-        self.pose_writer.write(&self.destination, None).unwrap();
+        self.move_to(MoveType::MoveLXyz, self.poses.above_color_sensor);
         self.state = State::LiftUpFromColor;
     }
 
     pub fn move_to_red(&mut self) {
-        self.move_block_to(State::MoveToRed, BLOCK_DISPOSE_RED);
+        self.swing_block_to(State::MoveToRed, self.poses.dispose_red);
     }
 
     pub fn move_to_green(&mut self) {
-        self.move_block_to(State::MoveToGreen, BLOCK_DISPOSE_GREEN);
+        self.swing_block_to(State::MoveToGreen, self.poses.dispose_green);
     }
 
     pub fn move_to_blue(&mut self) {
-        self.move_block_to(State::MoveToBlue, BLOCK_DISPOSE_BLUE);
+        self.swing_block_to(State::MoveToBlue, self.poses.dispose_blue);
     }
 
     pub fn move_to_mixed(&mut self) {
-        self.move_block_to(State::MoveToMixed, BLOCK_DISPOSE_MIXED);
+        self.swing_block_to(State::MoveToMixed, self.poses.dispose_mixed);
+    }
+
+    /// Wait for the arm to be homed again, without moving it.
+    pub fn rehome(&mut self) {
+        self.state = State::Rehoming { is_started: false };
+        self.conveyor_belt_writer
+            .write(&MotorSpeed { speed: 0 }, None)
+            .unwrap();
     }
 
     pub fn drop_block(&mut self) {
@@ -204,5 +332,6 @@ impl Controller {
         self.suction_writer
             .write(&Suction { is_on: false }, None)
             .unwrap();
+        self.switch_profile(MotionProfile::Fast);
     }
 }
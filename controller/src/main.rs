@@ -1,20 +1,26 @@
 mod controller;
 
 use crate::controller::CONVEYOR_BELT_SPEED;
-use controller::{Controller, State};
+use controller::{Controller, Poses, State};
 use dust_dds::{
     domain::domain_participant_factory::DomainParticipantFactory,
     infrastructure::{listeners::NoOpListener, qos::QosKind, status::NO_STATUS},
     subscription::{
         data_reader::DataReader,
-        sample_info::{ANY_INSTANCE_STATE, ANY_SAMPLE_STATE, ANY_VIEW_STATE},
+        sample_info::{SampleStateKind, ANY_INSTANCE_STATE, ANY_SAMPLE_STATE, ANY_VIEW_STATE},
     },
 };
 use std::{
-    io::{stdout, Write},
+    io::{stdout, ErrorKind, Write},
+    path::PathBuf,
     time::Instant,
 };
-use types::{Color, DobotPose, MotorSpeed, Presence, SensorState, Suction};
+use types::{
+    pose_library::{PoseLibrary, DEFAULT_POSE_LIBRARY},
+    ClearAlarms, Color, DobotHoming, DobotLostSteps, DobotMotionProfile, DobotMoveCommand,
+    DobotPose, DobotQueueControl, HomeDobot, HomingState, MotorSpeed, Presence, QueueAction,
+    SensorState, Suction,
+};
 
 const LOOP_PERIOD: std::time::Duration = std::time::Duration::from_millis(5);
 
@@ -28,6 +34,32 @@ fn show_dobot_pose(pose: &Option<DobotPose>) -> String {
     }
 }
 
+/// Load the poses from the pose library given as the only argument, or from the default one.
+fn load_poses() -> Poses {
+    let path = PathBuf::from(
+        std::env::args()
+            .nth(1)
+            .unwrap_or_else(|| DEFAULT_POSE_LIBRARY.to_string()),
+    );
+    match PoseLibrary::load(&path) {
+        Ok(library) => {
+            println!("Poses loaded from {}", path.display());
+            Poses::from_library(&library)
+        }
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            println!(
+                "No pose library at {}, using the built-in poses",
+                path.display()
+            );
+            Poses::default()
+        }
+        Err(error) => {
+            eprintln!("Failed to load pose library {}: {}", path.display(), error);
+            std::process::exit(1);
+        }
+    }
+}
+
 fn is_sensor_available(reader: &DataReader<SensorState>) -> bool {
     if let Ok(sample_list) = reader.read(1, ANY_SAMPLE_STATE, ANY_VIEW_STATE, ANY_INSTANCE_STATE) {
         if let Some(sample) = sample_list.first() {
@@ -40,7 +72,16 @@ fn is_sensor_available(reader: &DataReader<SensorState>) -> bool {
     }
 }
 
+/// The homing state last published by the `dobot` node.
+fn homing_state(reader: &DataReader<DobotHoming>) -> Option<HomingState> {
+    let sample_list = reader
+        .read(1, ANY_SAMPLE_STATE, ANY_VIEW_STATE, ANY_INSTANCE_STATE)
+        .ok()?;
+    sample_list.first()?.data().ok()?.state().ok()
+}
+
 fn main() {
+    let poses = load_poses();
     let domain_id = 0;
 
     let participant_factory = DomainParticipantFactory::get_instance();
@@ -160,10 +201,97 @@ fn main() {
         )
         .unwrap();
 
+    let topic_lost_steps = participant
+        .create_topic::<DobotLostSteps>(
+            "DobotLostSteps",
+            "DobotLostSteps",
+            QosKind::Default,
+            NoOpListener::new(),
+            NO_STATUS,
+        )
+        .unwrap();
+    let lost_steps_reader = subscriber
+        .create_datareader(
+            &topic_lost_steps,
+            QosKind::Default,
+            NoOpListener::new(),
+            NO_STATUS,
+        )
+        .unwrap();
+    let topic_homing = participant
+        .create_topic::<DobotHoming>(
+            "DobotHoming",
+            "DobotHoming",
+            QosKind::Default,
+            NoOpListener::new(),
+            NO_STATUS,
+        )
+        .unwrap();
+    let homing_reader = subscriber
+        .create_datareader(
+            &topic_homing,
+            QosKind::Default,
+            NoOpListener::new(),
+            NO_STATUS,
+        )
+        .unwrap();
+
     let publisher = participant
         .create_publisher(QosKind::Default, NoOpListener::new(), NO_STATUS)
         .unwrap();
 
+    let topic_clear_alarms = participant
+        .create_topic::<ClearAlarms>(
+            "ClearDobotAlarms",
+            "ClearAlarms",
+            QosKind::Default,
+            NoOpListener::new(),
+            NO_STATUS,
+        )
+        .unwrap();
+    let clear_alarms_writer = publisher
+        .create_datawriter(
+            &topic_clear_alarms,
+            QosKind::Default,
+            NoOpListener::new(),
+            NO_STATUS,
+        )
+        .unwrap();
+    let topic_home = participant
+        .create_topic::<HomeDobot>(
+            "HomeDobot",
+            "HomeDobot",
+            QosKind::Default,
+            NoOpListener::new(),
+            NO_STATUS,
+        )
+        .unwrap();
+    let home_writer = publisher
+        .create_datawriter(
+            &topic_home,
+            QosKind::Default,
+            NoOpListener::new(),
+            NO_STATUS,
+        )
+        .unwrap();
+    let topic_queue_control = participant
+        .create_topic::<DobotQueueControl>(
+            "DobotQueueControl",
+            "DobotQueueControl",
+            QosKind::Default,
+            NoOpListener::new(),
+            NO_STATUS,
+        )
+        .unwrap();
+    let queue_control_writer = publisher
+        .create_datawriter(
+            &topic_queue_control,
+            QosKind::Default,
+            NoOpListener::new(),
+            NO_STATUS,
+        )
+        .unwrap();
+
     let topic_conveyor_belt_speed = participant
         .create_topic::<MotorSpeed>(
             "ConveyorBeltSpeed",
@@ -173,10 +301,10 @@ fn main() {
             NO_STATUS,
         )
         .unwrap();
-    let topic_pose = participant
-        .create_topic::<DobotPose>(
-            "DobotArmMovement",
-            "DobotPose",
+    let topic_move_command = participant
+        .create_topic::<DobotMoveCommand>(
+            "DobotArmMoveCommand",
+            "DobotMoveCommand",
             QosKind::Default,
             NoOpListener::new(),
             NO_STATUS,
@@ -191,6 +319,15 @@ fn main() {
             NO_STATUS,
         )
         .unwrap();
+    let topic_motion_profile = participant
+        .create_topic::<DobotMotionProfile>(
+            "DobotMotionProfile",
+            "DobotMotionProfile",
+            QosKind::Default,
+            NoOpListener::new(),
+            NO_STATUS,
+        )
+        .unwrap();
 
     let mut controller = Controller::new(
         publisher
@@ -203,7 +340,7 @@ fn main() {
             .unwrap(),
         publisher
             .create_datawriter(
-                &topic_pose,
+                &topic_move_command,
                 QosKind::Default,
                 NoOpListener::new(),
                 NO_STATUS,
@@ -217,6 +354,15 @@ fn main() {
                 NO_STATUS,
             )
             .unwrap(),
+        publisher
+            .create_datawriter(
+                &topic_motion_profile,
+                QosKind::Default,
+                NoOpListener::new(),
+                NO_STATUS,
+            )
+            .unwrap(),
+        poses,
     );
 
     controller.initial();
@@ -236,6 +382,31 @@ fn main() {
             None
         };
 
+        // The coordinates have drifted, drop the moves which rely on them and
+        // start over once the arm is homed again.
+        if let Ok(sample_list) = lost_steps_reader.read(
+            1,
+            &[SampleStateKind::NotRead],
+            ANY_VIEW_STATE,
+            ANY_INSTANCE_STATE,
+        ) {
+            if let Some(sample) = sample_list.first() {
+                if let Ok(DobotLostSteps { axis }) = sample.data() {
+                    if !matches!(controller.state, State::Rehoming { .. }) {
+                        println!("\nLOST STEPS on axis {}, homing", axis + 1);
+                        queue_control_writer
+                            .write(&DobotQueueControl::new(QueueAction::Abort), None)
+                            .unwrap();
+                        clear_alarms_writer
+                            .write(&ClearAlarms { clear: true }, None)
+                            .unwrap();
+                        home_writer.write(&HomeDobot { home: true }, None).unwrap();
+                        controller.rehome();
+                    }
+                }
+            }
+        }
+
         if !is_sensor_available(&presence_sensor_availability_reader) {
             controller
                 .conveyor_belt_writer
@@ -329,6 +500,19 @@ fn main() {
                 }
             }
 
+            State::Rehoming { is_started } => match homing_state(&homing_reader) {
+                Some(HomingState::Homing) => {
+                    controller.state = State::Rehoming { is_started: true };
+                }
+                Some(HomingState::Homed) if is_started => controller.initial(),
+                Some(HomingState::Failed) if is_started => {
+                    println!("\nHOMING FAILED, homing again");
+                    home_writer.write(&HomeDobot { home: true }, None).unwrap();
+                    controller.rehome();
+                }
+                _ => (),
+            },
+
             State::DropBlock => {
                 if let Ok(sample_list) =
                     suction_reader.read(1, ANY_SAMPLE_STATE, ANY_VIEW_STATE, ANY_INSTANCE_STATE)
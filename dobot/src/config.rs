@@ -0,0 +1,429 @@
+use crate::dobot::{
+    base::{DEFAULT_BAUD_RATE, DEFAULT_PORT},
+    command::{IoFunction, SensorPort, SensorVersion},
+};
+use std::path::PathBuf;
+use types::{pose_library::DEFAULT_POSE_LIBRARY, MotionProfile};
+
+/// Address the emulator listens on when none is given.
+pub const DEFAULT_EMULATOR_ADDR: &str = "127.0.0.1:5000";
+const DEFAULT_PROGRAM: &str = "program.txt";
+
+pub const USAGE: &str = "\
+Usage: dobot [OPTIONS]
+       dobot emulator [HOST:PORT]
+       dobot play <PATH> [OPTIONS]
+
+Commands:
+    emulator            serve an emulated arm over TCP [default: 127.0.0.1:5000]
+    play                play a program file on the arm and exit
+
+Options:
+    -h, --help          print this help
+    --port <PATH>       serial port of the arm [default: /dev/ttyUSB0]
+    --baud <RATE>       baud rate of the serial port [default: 115200]
+    --tcp <HOST:PORT>   connect through a TCP serial gateway instead of a serial port
+    --emulator          run against an in-process emulated arm
+    --domain <ID>       DDS domain id [default: 0]
+    --tool-offset <X,Y,Z>
+                        offset of the tool center point in mm, set at startup
+                        [default: the offset stored on the arm]
+    --digital-input <PIN>
+                        publish the level of an extended I/O pin, repeatable
+    --digital-output <PIN>
+                        set the level of an extended I/O pin, repeatable
+    --pwm-output <PIN>  output a PWM signal on an extended I/O pin, repeatable
+    --analog-input <PIN>
+                        publish the reading of an extended I/O pin, repeatable
+    --color-sensor <PORT>
+                        publish the colour sensor of the sensor kit plugged into
+                        GP1, GP2, GP4 or GP5
+    --ir-switch <PORT>  publish the IR switch of the sensor kit plugged into
+                        GP1, GP2, GP4 or GP5
+    --sensor-version <VERSION>
+                        version 1 or 2 of the sensor kit [default: 1]
+    --home <WHEN>       home the arm at startup: always, never, or auto to home
+                        only if the arm was switched off since it was homed
+                        [default: always]
+    --home-position <X,Y,Z,R>
+                        position the arm moves to after homing, set at startup
+                        [default: the position stored on the arm]
+    --home-record <PATH>
+                        file in which homing is recorded for auto homing
+                        [default: dobot-home in the temporary directory]
+    --lost-step-threshold <DEGREES>
+                        check for lost steps after every PTP and arc move,
+                        with the given tolerance [default: no checks]
+    --motion-profile <NAME>
+                        motion profile at startup: gentle, fast or carry
+                        [default: fast]
+    --teach <NAME,...>  record a pose under each name in turn, taken when the
+                        unlock button on the forearm is released
+    --pose-library <PATH>
+                        file taught poses are recorded in [default: poses.txt]
+    --program <PATH>    program file played and recorded over DDS
+                        [default: program.txt]
+    --record            append the pose to the program whenever the unlock
+                        button is released, and the suction and belt commands
+                        received over DDS
+";
+
+/// How the node reaches the arm.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Connection {
+    Serial { port: String, baud_rate: u32 },
+    Tcp(String),
+    Emulator,
+}
+
+/// What the `dobot` program was asked to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Run the DDS node.
+    Run(Config),
+    /// Serve an emulated arm on the given address.
+    Emulator { listen: String },
+    /// Play a program file on the arm.
+    Play { program: PathBuf, config: Config },
+}
+
+/// Parse the command line arguments, without the program name.
+pub fn parse_args<I>(args: I) -> Result<Command, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter().peekable();
+    match args.peek().map(String::as_str) {
+        Some("emulator") => {
+            args.next();
+            let listen = args
+                .next()
+                .unwrap_or_else(|| DEFAULT_EMULATOR_ADDR.to_string());
+            if let Some(arg) = args.next() {
+                return Err(format!("unexpected argument {}", arg));
+            }
+            Ok(Command::Emulator { listen })
+        }
+        Some("play") => {
+            args.next();
+            let program = args.next().ok_or("missing program file")?;
+            let config = Config::from_args(args)?;
+            Ok(Command::Play {
+                program: PathBuf::from(program),
+                config,
+            })
+        }
+        _ => Config::from_args(args).map(Command::Run),
+    }
+}
+
+/// When the node homes the arm at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Homing {
+    Always,
+    Never,
+    /// Home unless the arm has kept its calibration since it was last homed.
+    Auto,
+}
+
+/// Addresses of the extended I/O pins.
+const IO_PINS: std::ops::RangeInclusive<u8> = 1..=20;
+
+/// Command line configuration of the `dobot` node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub connection: Connection,
+    pub domain_id: i32,
+    /// X, Y and Z bias of the end effector.
+    pub tool_offset: Option<[f32; 3]>,
+    /// Extended I/O pins bridged to DDS, with the function they are set to.
+    pub io_pins: Vec<(u8, IoFunction)>,
+    /// Port of the colour sensor of the sensor kit, published instead of the `color_sensor` node.
+    pub color_sensor: Option<SensorPort>,
+    /// Port of the IR switch of the sensor kit, published instead of the `presence_sensor` node.
+    pub ir_switch: Option<SensorPort>,
+    pub sensor_version: SensorVersion,
+    pub homing: Homing,
+    pub home_position: Option<[f32; 4]>,
+    pub home_record: PathBuf,
+    /// Tolerance of the lost-step checks in degrees, no checks without one.
+    pub lost_step_threshold: Option<f32>,
+    pub motion_profile: MotionProfile,
+    /// Names of the poses to teach, in order.
+    pub teach: Vec<String>,
+    pub pose_library: PathBuf,
+    pub program: PathBuf,
+    /// Whether the unlock button and the suction and belt commands are recorded.
+    pub is_recording: bool,
+}
+
+impl Config {
+    /// Parse the command line arguments, without the program name.
+    pub fn from_args<I>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut port = DEFAULT_PORT.to_string();
+        let mut baud_rate = DEFAULT_BAUD_RATE;
+        let mut tcp = None;
+        let mut emulator = false;
+        let mut domain_id = 0;
+        let mut tool_offset = None;
+        let mut io_pins = Vec::new();
+        let mut color_sensor = None;
+        let mut ir_switch = None;
+        let mut sensor_version = SensorVersion::V1;
+        let mut homing = Homing::Always;
+        let mut home_position = None;
+        let mut home_record = std::env::temp_dir().join("dobot-home");
+        let mut lost_step_threshold = None;
+        let mut motion_profile = MotionProfile::Fast;
+        let mut teach = Vec::new();
+        let mut pose_library = PathBuf::from(DEFAULT_POSE_LIBRARY);
+        let mut program = PathBuf::from(DEFAULT_PROGRAM);
+        let mut is_recording = false;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--port" => port = value()?,
+                "--baud" => baud_rate = parse(&value()?)?,
+                "--tcp" => tcp = Some(value()?),
+                "--emulator" => emulator = true,
+                "--domain" => domain_id = parse(&value()?)?,
+                "--tool-offset" => tool_offset = Some(parse_coordinates(&value()?, "X,Y,Z")?),
+                "--digital-input" => {
+                    io_pins.push((parse_pin(&value()?)?, IoFunction::DigitalInput))
+                }
+                "--digital-output" => {
+                    io_pins.push((parse_pin(&value()?)?, IoFunction::DigitalOutput))
+                }
+                "--pwm-output" => io_pins.push((parse_pin(&value()?)?, IoFunction::Pwm)),
+                "--analog-input" => io_pins.push((parse_pin(&value()?)?, IoFunction::Adc)),
+                "--color-sensor" => color_sensor = Some(parse_port(&value()?)?),
+                "--ir-switch" => ir_switch = Some(parse_port(&value()?)?),
+                "--sensor-version" => {
+                    sensor_version = match value()?.as_str() {
+                        "1" => SensorVersion::V1,
+                        "2" => SensorVersion::V2,
+                        version => return Err(format!("unknown sensor version {}", version)),
+                    }
+                }
+                "--home" => {
+                    homing = match value()?.as_str() {
+                        "always" => Homing::Always,
+                        "never" => Homing::Never,
+                        "auto" => Homing::Auto,
+                        when => return Err(format!("unknown homing {}", when)),
+                    }
+                }
+                "--home-position" => home_position = Some(parse_coordinates(&value()?, "X,Y,Z,R")?),
+                "--home-record" => home_record = PathBuf::from(value()?),
+                "--lost-step-threshold" => lost_step_threshold = Some(parse(&value()?)?),
+                "--motion-profile" => {
+                    motion_profile = match value()?.as_str() {
+                        "gentle" => MotionProfile::Gentle,
+                        "fast" => MotionProfile::Fast,
+                        "carry" => MotionProfile::Carry,
+                        name => return Err(format!("unknown motion profile {}", name)),
+                    }
+                }
+                "--teach" => teach = parse_names(&value()?)?,
+                "--pose-library" => pose_library = PathBuf::from(value()?),
+                "--program" => program = PathBuf::from(value()?),
+                "--record" => is_recording = true,
+                "--help" | "-h" => {
+                    print!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+
+        let connection = match (tcp, emulator) {
+            (Some(_), true) => return Err("--tcp and --emulator are exclusive".to_string()),
+            (Some(addr), false) => Connection::Tcp(addr),
+            (None, true) => Connection::Emulator,
+            (None, false) => Connection::Serial { port, baud_rate },
+        };
+
+        Ok(Self {
+            connection,
+            domain_id,
+            tool_offset,
+            io_pins,
+            color_sensor,
+            ir_switch,
+            sensor_version,
+            homing,
+            home_position,
+            home_record,
+            lost_step_threshold,
+            motion_profile,
+            teach,
+            pose_library,
+            program,
+            is_recording,
+        })
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {}", value))
+}
+
+/// Parse comma separated coordinates, such as `X,Y,Z`.
+fn parse_coordinates<const N: usize>(value: &str, format: &str) -> Result<[f32; N], String> {
+    let coordinates = value
+        .split(',')
+        .map(|coordinate| parse(coordinate.trim()))
+        .collect::<Result<Vec<f32>, _>>()?;
+    coordinates
+        .try_into()
+        .map_err(|_| format!("expected {} instead of {}", format, value))
+}
+
+/// Parse comma separated names, which are stored without whitespace.
+fn parse_names(value: &str) -> Result<Vec<String>, String> {
+    value
+        .split(',')
+        .map(|name| match name.trim() {
+            "" => Err(format!("empty name in {}", value)),
+            name if name.contains(char::is_whitespace) => {
+                Err(format!("name {} contains whitespace", name))
+            }
+            name => Ok(name.to_string()),
+        })
+        .collect()
+}
+
+fn parse_pin(value: &str) -> Result<u8, String> {
+    let address = parse(value)?;
+    match IO_PINS.contains(&address) {
+        true => Ok(address),
+        false => Err(format!("I/O pin {} is not between 1 and 20", address)),
+    }
+}
+
+fn parse_port(value: &str) -> Result<SensorPort, String> {
+    match value.to_ascii_uppercase().as_str() {
+        "GP1" => Ok(SensorPort::Gp1),
+        "GP2" => Ok(SensorPort::Gp2),
+        "GP4" => Ok(SensorPort::Gp4),
+        "GP5" => Ok(SensorPort::Gp5),
+        _ => Err(format!("unknown sensor port {}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn run_config(line: &str) -> Result<Config, String> {
+        match parse_args(args(line))? {
+            Command::Run(config) => Ok(config),
+            command => panic!("expected the node to run, got {:?}", command),
+        }
+    }
+
+    #[test]
+    fn defaults_to_the_serial_port() {
+        let config = run_config("").unwrap();
+        assert_eq!(
+            config.connection,
+            Connection::Serial {
+                port: DEFAULT_PORT.to_string(),
+                baud_rate: DEFAULT_BAUD_RATE,
+            }
+        );
+        assert_eq!(config.homing, Homing::Always);
+        assert_eq!(config.motion_profile, MotionProfile::Fast);
+        assert!(config.io_pins.is_empty());
+    }
+
+    #[test]
+    fn parses_the_connection() {
+        let config = run_config("--tcp 10.0.0.2:8899 --domain 3").unwrap();
+        assert_eq!(
+            config.connection,
+            Connection::Tcp("10.0.0.2:8899".to_string())
+        );
+        assert_eq!(config.domain_id, 3);
+
+        let config = run_config("--emulator").unwrap();
+        assert_eq!(config.connection, Connection::Emulator);
+    }
+
+    #[test]
+    fn rejects_tcp_with_the_emulator() {
+        assert!(run_config("--tcp 10.0.0.2:8899 --emulator").is_err());
+        assert!(run_config("--emulator --tcp 10.0.0.2:8899").is_err());
+    }
+
+    #[test]
+    fn parses_io_pins_in_order() {
+        let config = run_config("--digital-input 1 --pwm-output 20 --analog-input 7").unwrap();
+        assert_eq!(
+            config.io_pins,
+            vec![
+                (1, IoFunction::DigitalInput),
+                (20, IoFunction::Pwm),
+                (7, IoFunction::Adc),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_pins() {
+        assert!(run_config("--digital-input 0").is_err());
+        assert!(run_config("--digital-output 21").is_err());
+        assert!(run_config("--pwm-output -1").is_err());
+        assert!(run_config("--analog-input pin").is_err());
+        assert!(run_config("--digital-input").is_err());
+    }
+
+    #[test]
+    fn parses_coordinates_and_names() {
+        let config = run_config("--tool-offset 59.7,0,-3.5 --teach pick,red,green").unwrap();
+        assert_eq!(config.tool_offset, Some([59.7, 0.0, -3.5]));
+        assert_eq!(config.teach, vec!["pick", "red", "green"]);
+
+        assert!(run_config("--tool-offset 1,2").is_err());
+        assert!(run_config("--home-position 1,2,3").is_err());
+        assert!(run_config("--teach pick,,red").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_arguments() {
+        assert!(run_config("--speed 3").is_err());
+        assert!(run_config("--color-sensor GP3").is_err());
+        assert!(run_config("--home sometimes").is_err());
+    }
+
+    #[test]
+    fn parses_the_commands() {
+        assert_eq!(
+            parse_args(args("emulator")),
+            Ok(Command::Emulator {
+                listen: DEFAULT_EMULATOR_ADDR.to_string(),
+            })
+        );
+        assert!(parse_args(args("emulator 0.0.0.0:5000 extra")).is_err());
+
+        match parse_args(args("play moves.txt --emulator")).unwrap() {
+            Command::Play { program, config } => {
+                assert_eq!(program, PathBuf::from("moves.txt"));
+                assert_eq!(config.connection, Connection::Emulator);
+            }
+            command => panic!("expected a program to play, got {:?}", command),
+        }
+        assert!(parse_args(args("play")).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+use crate::dobot::{
+    command::{Params, ParamsReader},
+    error::Result as DobotResult,
+};
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use std::fmt;
+
+/// Number of bytes of the alarm bitset reported by the arm.
+const ALARMS_LEN: usize = 16;
+
+/// An alarm of the Dobot Magician, identified by its alarm code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromPrimitive)]
+pub enum Alarm {
+    Reset = 0x00,
+    UndefinedInstruction = 0x01,
+    FileSystem = 0x02,
+    McuFpgaCommunication = 0x03,
+    AngleSensor = 0x04,
+
+    PlanSingularity = 0x10,
+    PlanInverseCalculation = 0x11,
+    PlanInverseLimit = 0x12,
+    PlanDataRepeat = 0x13,
+    PlanArcInput = 0x14,
+    PlanJumpParam = 0x15,
+
+    MoveSingularity = 0x20,
+    MoveInverseCalculation = 0x21,
+    MoveInverseLimit = 0x22,
+
+    OverspeedAxis1 = 0x30,
+    OverspeedAxis2 = 0x31,
+    OverspeedAxis3 = 0x32,
+    OverspeedAxis4 = 0x33,
+
+    LimitAxis1Positive = 0x40,
+    LimitAxis1Negative = 0x41,
+    LimitAxis2Positive = 0x42,
+    LimitAxis2Negative = 0x43,
+    LimitAxis3Positive = 0x44,
+    LimitAxis3Negative = 0x45,
+    LimitAxis4Positive = 0x46,
+    LimitAxis4Negative = 0x47,
+    LimitAxis23Positive = 0x48,
+    LimitAxis23Negative = 0x49,
+
+    LostStepAxis1 = 0x50,
+    LostStepAxis2 = 0x51,
+    LostStepAxis3 = 0x52,
+    LostStepAxis4 = 0x53,
+
+    MotorDriverAxis1 = 0x60,
+    MotorDriverAxis2 = 0x61,
+    MotorDriverAxis3 = 0x62,
+    MotorDriverAxis4 = 0x63,
+    MotorOverflowAxis1 = 0x64,
+    MotorOverflowAxis2 = 0x65,
+    MotorOverflowAxis3 = 0x66,
+    MotorOverflowAxis4 = 0x67,
+    MotorFollowingAxis1 = 0x68,
+    MotorFollowingAxis2 = 0x69,
+    MotorFollowingAxis3 = 0x6a,
+    MotorFollowingAxis4 = 0x6b,
+}
+
+/// Groups of related alarms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlarmCategory {
+    Common,
+    Plan,
+    Move,
+    Overspeed,
+    Limit,
+    LostStep,
+    Motor,
+}
+
+impl Alarm {
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    pub fn category(self) -> AlarmCategory {
+        match self.code() >> 4 {
+            0x0 => AlarmCategory::Common,
+            0x1 => AlarmCategory::Plan,
+            0x2 => AlarmCategory::Move,
+            0x3 => AlarmCategory::Overspeed,
+            0x4 => AlarmCategory::Limit,
+            0x5 => AlarmCategory::LostStep,
+            _ => AlarmCategory::Motor,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Alarm::Reset => "controller was reset",
+            Alarm::UndefinedInstruction => "undefined instruction",
+            Alarm::FileSystem => "file system error",
+            Alarm::McuFpgaCommunication => "communication between MCU and FPGA failed",
+            Alarm::AngleSensor => "angle sensor reading error",
+            Alarm::PlanSingularity => "planned target is a singular point",
+            Alarm::PlanInverseCalculation => "inverse kinematics of planned target failed",
+            Alarm::PlanInverseLimit => "planned target is out of range",
+            Alarm::PlanDataRepeat => "planned target repeats the current position",
+            Alarm::PlanArcInput => "arc points are invalid",
+            Alarm::PlanJumpParam => "jump parameters are invalid",
+            Alarm::MoveSingularity => "motion passes a singular point",
+            Alarm::MoveInverseCalculation => "inverse kinematics during motion failed",
+            Alarm::MoveInverseLimit => "motion leaves the working range",
+            Alarm::OverspeedAxis1 => "axis 1 overspeed",
+            Alarm::OverspeedAxis2 => "axis 2 overspeed",
+            Alarm::OverspeedAxis3 => "axis 3 overspeed",
+            Alarm::OverspeedAxis4 => "axis 4 overspeed",
+            Alarm::LimitAxis1Positive => "axis 1 positive limit",
+            Alarm::LimitAxis1Negative => "axis 1 negative limit",
+            Alarm::LimitAxis2Positive => "axis 2 positive limit",
+            Alarm::LimitAxis2Negative => "axis 2 negative limit",
+            Alarm::LimitAxis3Positive => "axis 3 positive limit",
+            Alarm::LimitAxis3Negative => "axis 3 negative limit",
+            Alarm::LimitAxis4Positive => "axis 4 positive limit",
+            Alarm::LimitAxis4Negative => "axis 4 negative limit",
+            Alarm::LimitAxis23Positive => "axes 2 and 3 positive limit",
+            Alarm::LimitAxis23Negative => "axes 2 and 3 negative limit",
+            Alarm::LostStepAxis1 => "axis 1 lost steps",
+            Alarm::LostStepAxis2 => "axis 2 lost steps",
+            Alarm::LostStepAxis3 => "axis 3 lost steps",
+            Alarm::LostStepAxis4 => "axis 4 lost steps",
+            Alarm::MotorDriverAxis1 => "axis 1 motor driver fault",
+            Alarm::MotorDriverAxis2 => "axis 2 motor driver fault",
+            Alarm::MotorDriverAxis3 => "axis 3 motor driver fault",
+            Alarm::MotorDriverAxis4 => "axis 4 motor driver fault",
+            Alarm::MotorOverflowAxis1 => "axis 1 motor overflow",
+            Alarm::MotorOverflowAxis2 => "axis 2 motor overflow",
+            Alarm::MotorOverflowAxis3 => "axis 3 motor overflow",
+            Alarm::MotorOverflowAxis4 => "axis 4 motor overflow",
+            Alarm::MotorFollowingAxis1 => "axis 1 motor following error",
+            Alarm::MotorFollowingAxis2 => "axis 2 motor following error",
+            Alarm::MotorFollowingAxis3 => "axis 3 motor following error",
+            Alarm::MotorFollowingAxis4 => "axis 4 motor following error",
+        }
+    }
+}
+
+impl fmt::Display for Alarm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#04x} {}", self.code(), self.description())
+    }
+}
+
+/// The set of active alarms, one bit per alarm code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Alarms {
+    bits: [u8; ALARMS_LEN],
+}
+
+impl Alarms {
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|&byte| byte == 0)
+    }
+
+    pub fn contains_code(&self, code: u8) -> bool {
+        self.bits
+            .get(code as usize / 8)
+            .is_some_and(|byte| byte & (1 << (code % 8)) != 0)
+    }
+
+    pub fn contains(&self, alarm: Alarm) -> bool {
+        self.contains_code(alarm.code())
+    }
+
+    pub fn insert(&mut self, alarm: Alarm) {
+        self.bits[alarm.code() as usize / 8] |= 1 << (alarm.code() % 8);
+    }
+
+    pub fn clear(&mut self) {
+        self.bits = Default::default();
+    }
+
+    /// Codes of all active alarms, including codes which are not known.
+    pub fn codes(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..(ALARMS_LEN * 8) as u8).filter(|&code| self.contains_code(code))
+    }
+
+    /// All active alarms with a known code.
+    pub fn iter(&self) -> impl Iterator<Item = Alarm> + '_ {
+        self.codes().filter_map(Alarm::from_u8)
+    }
+
+    pub fn has_category(&self, category: AlarmCategory) -> bool {
+        self.iter().any(|alarm| alarm.category() == category)
+    }
+}
+
+impl Params for Alarms {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.bits);
+    }
+
+    fn decode(reader: &mut ParamsReader) -> DobotResult<Self> {
+        // Firmware versions differ in the length of the bitset.
+        let mut alarms = Self::default();
+        for (bits, &byte) in alarms.bits.iter_mut().zip(reader.take_remaining()) {
+            *bits = byte;
+        }
+        Ok(alarms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dobot::command::decode_params;
+
+    #[test]
+    fn indexes_the_first_and_the_last_alarm() {
+        let mut bytes = [0u8; ALARMS_LEN];
+        bytes[0] = 0x01;
+        bytes[0x6b / 8] = 1 << (0x6b % 8);
+        let alarms: Alarms = decode_params(&bytes).unwrap();
+
+        assert!(alarms.contains(Alarm::Reset));
+        assert!(alarms.contains(Alarm::MotorFollowingAxis4));
+        assert!(!alarms.contains(Alarm::UndefinedInstruction));
+        assert!(!alarms.contains(Alarm::MotorFollowingAxis3));
+        assert_eq!(alarms.codes().collect::<Vec<_>>(), [0x00, 0x6b]);
+        assert_eq!(
+            alarms.iter().collect::<Vec<_>>(),
+            [Alarm::Reset, Alarm::MotorFollowingAxis4]
+        );
+    }
+
+    #[test]
+    fn encodes_inserted_alarms() {
+        let mut alarms = Alarms::default();
+        assert!(alarms.is_empty());
+        alarms.insert(Alarm::Reset);
+        alarms.insert(Alarm::MotorFollowingAxis4);
+
+        let mut bytes = Vec::new();
+        alarms.encode(&mut bytes);
+        assert_eq!(bytes.len(), ALARMS_LEN);
+        assert_eq!(bytes[0], 0x01);
+        assert_eq!(bytes[13], 0x08);
+        assert_eq!(decode_params::<Alarms>(&bytes).unwrap(), alarms);
+
+        alarms.clear();
+        assert!(alarms.is_empty());
+    }
+
+    #[test]
+    fn keeps_unknown_codes() {
+        let mut bytes = [0u8; ALARMS_LEN];
+        bytes[ALARMS_LEN - 1] = 0x80;
+        let alarms: Alarms = decode_params(&bytes).unwrap();
+
+        assert!(!alarms.is_empty());
+        assert_eq!(alarms.codes().collect::<Vec<_>>(), [0x7f]);
+        assert_eq!(alarms.iter().count(), 0);
+        assert!(!alarms.contains_code(0x80));
+    }
+
+    #[test]
+    fn decodes_bitsets_of_any_length() {
+        let alarms: Alarms = decode_params(&[0x00, 0x00, 0x01]).unwrap();
+        assert_eq!(alarms.iter().collect::<Vec<_>>(), [Alarm::PlanSingularity]);
+
+        let alarms: Alarms = decode_params(&[0xff; ALARMS_LEN + 4]).unwrap();
+        assert_eq!(alarms.codes().count(), ALARMS_LEN * 8);
+    }
+
+    #[test]
+    fn groups_alarms_by_code() {
+        assert_eq!(Alarm::Reset.category(), AlarmCategory::Common);
+        assert_eq!(Alarm::PlanArcInput.category(), AlarmCategory::Plan);
+        assert_eq!(Alarm::LostStepAxis1.category(), AlarmCategory::LostStep);
+        assert_eq!(Alarm::LostStepAxis4.category(), AlarmCategory::LostStep);
+        assert_eq!(Alarm::MotorFollowingAxis4.category(), AlarmCategory::Motor);
+        assert_eq!(
+            Alarm::MotorFollowingAxis4.to_string(),
+            "0x6b axis 4 motor following error"
+        );
+    }
+}
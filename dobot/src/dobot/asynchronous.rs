@@ -0,0 +1,271 @@
+//! Asynchronous API on top of the blocking driver, for use with tokio.
+//!
+//! Requests are run on the blocking thread pool of tokio, one at a time, so
+//! that tasks can send commands, wait for queued commands and stream poses
+//! concurrently without blocking the runtime.
+
+use crate::dobot::{
+    base::Dobot,
+    command::{Command, Pose},
+    error::{Error as DobotError, Result as DobotResult},
+    message::DobotMessage,
+    transport::Transport,
+};
+use serial2::SerialPort;
+use std::{
+    io::Error as IoError,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc,
+    task,
+    time::{self, MissedTickBehavior},
+};
+
+/// A [`Dobot`] shared between tasks.
+///
+/// Cloning the handle does not open another connection; all clones send
+/// their requests over the same transport.
+pub struct AsyncDobot<T: Transport = SerialPort> {
+    dobot: Arc<Mutex<Dobot<T>>>,
+}
+
+impl<T: Transport> Clone for AsyncDobot<T> {
+    fn clone(&self) -> Self {
+        Self {
+            dobot: self.dobot.clone(),
+        }
+    }
+}
+
+impl<T: Transport + Send + 'static> AsyncDobot<T> {
+    pub fn new(dobot: Dobot<T>) -> Self {
+        Self {
+            dobot: Arc::new(Mutex::new(dobot)),
+        }
+    }
+
+    /// Run a blocking operation on the arm without blocking the runtime.
+    ///
+    /// Once an operation panicked, the arm may be left in the middle of a
+    /// request and all further operations fail.
+    pub async fn with<F, R>(&self, f: F) -> DobotResult<R>
+    where
+        F: FnOnce(&mut Dobot<T>) -> DobotResult<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let dobot = self.dobot.clone();
+        let operation = move || {
+            let mut dobot = dobot
+                .lock()
+                .map_err(|_| IoError::other("an earlier operation on the arm panicked"))?;
+            f(&mut dobot)
+        };
+        match task::spawn_blocking(operation).await {
+            Ok(result) => result,
+            Err(error) => match error.try_into_panic() {
+                Ok(panic) => std::panic::resume_unwind(panic),
+                Err(error) => Err(IoError::other(error).into()),
+            },
+        }
+    }
+
+    /// Send user-defined request to Dobot and obtain response.
+    pub async fn send_command(&self, request_msg: DobotMessage) -> DobotResult<DobotMessage> {
+        self.with(move |dobot| dobot.send_command(request_msg))
+            .await
+    }
+
+    /// Send a typed command and decode its response.
+    pub async fn execute<C>(&self, command: C) -> DobotResult<C::Response>
+    where
+        C: Command + Send + 'static,
+        C::Response: Send,
+    {
+        self.with(move |dobot| dobot.execute(&command)).await
+    }
+
+    /// Put a command into the command queue of the arm.
+    pub async fn queue<C>(&self, command: C) -> DobotResult<AsyncWaitHandle<T>>
+    where
+        C: Command + Send + 'static,
+    {
+        let (command_index, poll_interval) = self
+            .with(move |dobot| {
+                let handle = dobot.queue(command)?;
+                Ok((handle.index(), dobot.poll_interval()))
+            })
+            .await?;
+        Ok(AsyncWaitHandle {
+            command_index,
+            poll_interval,
+            dobot: self.clone(),
+        })
+    }
+
+    /// Get the current pose of robot, including the rail position if there is one.
+    pub async fn get_pose(&self) -> DobotResult<Pose> {
+        self.with(|dobot| dobot.get_pose()).await
+    }
+
+    /// Whether the queued command with the given index has been executed.
+    pub async fn is_queued_cmd_done(&self, command_index: u64) -> DobotResult<bool> {
+        self.with(move |dobot| dobot.is_queued_cmd_done(command_index))
+            .await
+    }
+
+    /// Read the pose periodically on a background task.
+    ///
+    /// The task ends when the returned stream is dropped. Poses which are
+    /// not received in time are skipped rather than buffered.
+    pub fn pose_stream(&self, period: Duration) -> PoseStream {
+        let (sender, receiver) = mpsc::channel(1);
+        let dobot = self.clone();
+        task::spawn(async move {
+            let mut interval = time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                let pose = dobot.get_pose().await;
+                if sender.send(pose).await.is_err() {
+                    break;
+                }
+            }
+        });
+        PoseStream { receiver }
+    }
+}
+
+/// A queued command which can be waited for asynchronously.
+pub struct AsyncWaitHandle<T: Transport = SerialPort> {
+    command_index: u64,
+    poll_interval: Duration,
+    dobot: AsyncDobot<T>,
+}
+
+impl<T: Transport + Send + 'static> AsyncWaitHandle<T> {
+    /// Queue index of the command.
+    pub fn index(&self) -> u64 {
+        self.command_index
+    }
+
+    /// Set how often the queue index is read while waiting.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Check once whether the command has been executed, without waiting.
+    pub async fn is_done(&self) -> DobotResult<bool> {
+        self.dobot.is_queued_cmd_done(self.command_index).await
+    }
+
+    /// Resolve once the command has been executed.
+    pub async fn wait(self) -> DobotResult<()> {
+        while !self.is_done().await? {
+            time::sleep(self.poll_interval).await;
+        }
+        Ok(())
+    }
+
+    /// Resolve once the command has been executed or the timeout expires.
+    ///
+    /// Giving up does not remove the command from the queue.
+    pub async fn wait_timeout(self, timeout: Duration) -> DobotResult<()> {
+        let index = self.command_index;
+        match time::timeout(timeout, self.wait()).await {
+            Ok(result) => result,
+            Err(_) => Err(DobotError::WaitTimeout { index }),
+        }
+    }
+}
+
+/// Poses read periodically by [`AsyncDobot::pose_stream`].
+pub struct PoseStream {
+    receiver: mpsc::Receiver<DobotResult<Pose>>,
+}
+
+impl PoseStream {
+    /// Wait for the next pose, or the error which occurred reading it.
+    pub async fn next(&mut self) -> Option<DobotResult<Pose>> {
+        self.receiver.recv().await
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use super::*;
+    use crate::dobot::{command::SetWaitCmd, emulator::Emulator, transport::PipeEnd};
+    use std::{
+        future::Future,
+        panic::{self, AssertUnwindSafe},
+    };
+    use tokio::runtime::Builder;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn emulated() -> AsyncDobot<PipeEnd> {
+        let dobot = Dobot::builder()
+            .poll_interval(Duration::from_millis(5))
+            .connect(Emulator::new().spawn())
+            .unwrap();
+        AsyncDobot::new(dobot)
+    }
+
+    #[test]
+    fn waits_for_a_queued_command() {
+        let dobot = emulated();
+        block_on(async {
+            let handle = dobot.queue(SetWaitCmd(50)).await.unwrap();
+            let index = handle.index();
+            assert!(!handle.is_done().await.unwrap());
+            handle.wait().await.unwrap();
+            assert!(dobot.is_queued_cmd_done(index).await.unwrap());
+        });
+    }
+
+    #[test]
+    fn gives_up_waiting_after_the_timeout() {
+        let dobot = emulated();
+        block_on(async {
+            let handle = dobot.queue(SetWaitCmd(500)).await.unwrap();
+            let index = handle.index();
+            let result = handle.wait_timeout(Duration::from_millis(50)).await;
+            assert!(matches!(result, Err(DobotError::WaitTimeout { index: i }) if i == index));
+            assert!(!dobot.is_queued_cmd_done(index).await.unwrap());
+        });
+    }
+
+    #[test]
+    fn streams_poses() {
+        let dobot = emulated();
+        block_on(async {
+            let expected = dobot.get_pose().await.unwrap();
+            let mut poses = dobot.pose_stream(Duration::from_millis(10));
+            for _ in 0..3 {
+                let pose = poses.next().await.unwrap().unwrap();
+                assert_eq!(pose.x, expected.x);
+                assert_eq!(pose.z, expected.z);
+            }
+        });
+    }
+
+    #[test]
+    fn fails_after_an_operation_panicked() {
+        let dobot = emulated();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            block_on(dobot.with(|_| -> DobotResult<()> { panic!("lost the arm") }))
+        }));
+        assert!(result.is_err());
+
+        let result = block_on(dobot.get_pose());
+        assert!(matches!(result, Err(DobotError::Io(_))));
+    }
+}
@@ -1,198 +1,385 @@
 use crate::dobot::{
+    alarm::Alarms,
+    command::{
+        AdcCondition, ArcCmd, ArcParams, CartesianPoint, ClearAllAlarmsState, ColorSensorReading,
+        Command, CpCmd, CpLeCmd, CpMode, CpParams, DeviceVersion, DownloadParams, EMotor,
+        EndEffectorGripper, EndEffectorLaser, EndEffectorParams, EndEffectorSuctionCup,
+        GetAlarmsState, GetArcParams, GetColorSensor, GetCpParams, GetDeviceName, GetDeviceSn,
+        GetDeviceTime, GetDeviceVersion, GetDeviceWithL, GetEndEffectorGripper,
+        GetEndEffectorLaser, GetEndEffectorParams, GetEndEffectorSuctionCup, GetHhtTrigMode,
+        GetHhtTrigOutput, GetHhtTrigOutputEnabled, GetHomeParams, GetIoAdc, GetIoDi, GetIoDo,
+        GetIoMultiplexing, GetIoPwm, GetIrSwitch, GetJogCommonParams, GetJogCoordinateParams,
+        GetJogJointParams, GetJogLParams, GetPose, GetPoseL, GetPtpCommonParams,
+        GetPtpCoordinateParams, GetPtpJointParams, GetPtpJump2Params, GetPtpJumpParams,
+        GetPtpLParams, GetQueuedCmdCurrentIndex, HhtTrigMode, HomeParams, IoAdc, IoFunction,
+        IoLevel, IoMultiplexing, IoPwm, JogCmd, JogCommand, JogCommonParams, JogCoordinateParams,
+        JogJointParams, JogLParams, Mode, ParallelOutputCmd, Pose, PtpCmd, PtpCommonParams,
+        PtpCoordinateParams, PtpJointParams, PtpJump2Params, PtpJumpParams, PtpLParams,
+        PtpPoWithLCmd, PtpWithLCmd, Queued, SensorParams, SensorPort, SensorVersion, SetArcCmd,
+        SetArcParams, SetColorSensor, SetCpCmd, SetCpLeCmd, SetCpParams, SetDeviceName,
+        SetDeviceWithL, SetEMotor, SetEndEffectorGripper, SetEndEffectorLaser,
+        SetEndEffectorParams, SetEndEffectorSuctionCup, SetHhtTrigMode, SetHhtTrigOutputEnabled,
+        SetHomeCmd, SetHomeParams, SetIoDo, SetIoMultiplexing, SetIoPwm, SetIrSwitch, SetJogCmd,
+        SetJogCommonParams, SetJogCoordinateParams, SetJogJointParams, SetJogLParams,
+        SetLostStepCmd, SetLostStepParams, SetPtpCmd, SetPtpCommonParams, SetPtpCoordinateParams,
+        SetPtpJointParams, SetPtpJump2Params, SetPtpJumpParams, SetPtpLParams, SetPtpPoWithLCmd,
+        SetPtpWithLCmd, SetQueuedCmdClear, SetQueuedCmdForceStopExec, SetQueuedCmdStartDownload,
+        SetQueuedCmdStartExec, SetQueuedCmdStopDownload, SetQueuedCmdStopExec, SetTrigCmd,
+        SetWaitCmd, TrigCmd,
+    },
     error::{Error as DobotError, Result as DobotResult},
-    message::DobotMessage,
+    message::{DobotMessage, FrameDecoder},
+    transport::Transport,
 };
 use num_derive::FromPrimitive;
 use serial2::SerialPort;
+use std::{
+    io::{Error as IoError, ErrorKind},
+    net::{TcpStream, ToSocketAddrs},
+    thread,
+    time::{Duration, Instant},
+};
 
-/// Defines the format to describe the robot pose.
-#[derive(Debug, Clone)]
-pub enum Mode {
-    #[allow(non_camel_case_types)]
-    _MODE_PTP_JUMP_XYZ = 0x00,
-    #[allow(non_camel_case_types)]
-    MODE_PTP_MOVJ_XYZ = 0x01,
-    #[allow(non_camel_case_types)]
-    _MODE_PTP_MOVL_XYZ = 0x02,
-    #[allow(non_camel_case_types)]
-    _MODE_PTP_JUMP_ANGLE = 0x03,
-    #[allow(non_camel_case_types)]
-    _MODE_PTP_MOVJ_ANGLE = 0x04,
-    #[allow(non_camel_case_types)]
-    _MODE_PTP_MOVL_ANGLE = 0x05,
-    #[allow(non_camel_case_types)]
-    _MODE_PTP_MOVJ_INC = 0x06,
-    #[allow(non_camel_case_types)]
-    _MODE_PTP_MOVL_INC = 0x07,
-    #[allow(non_camel_case_types)]
-    _MODE_PTP_MOVJ_XYZ_INC = 0x08,
-    #[allow(non_camel_case_types)]
-    _MODE_PTP_JUMP_MOVL_XYZ = 0x09,
+/// Serial port used when no other port is configured.
+pub const DEFAULT_PORT: &str = "/dev/ttyUSB0";
+
+/// Baud rate of the Dobot serial interface.
+pub const DEFAULT_BAUD_RATE: u32 = 115200;
+
+/// Time the arm is given to answer a request.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Time between two reads of the queue index while waiting for a command.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Joint velocities and accelerations set when connecting, unless configured otherwise.
+pub const DEFAULT_PTP_JOINT_PARAMS: PtpJointParams = PtpJointParams {
+    velocity: [200.0; 4],
+    acceleration: [200.0; 4],
+};
+
+/// Cartesian velocities and accelerations set when connecting, unless configured otherwise.
+pub const DEFAULT_PTP_COORDINATE_PARAMS: PtpCoordinateParams = PtpCoordinateParams {
+    xyz_velocity: 200.0,
+    r_velocity: 200.0,
+    xyz_acceleration: 200.0,
+    r_acceleration: 200.0,
+};
+
+/// Jump parameters set when connecting, unless configured otherwise.
+pub const DEFAULT_PTP_JUMP_PARAMS: PtpJumpParams = PtpJumpParams {
+    jump_height: 10.0,
+    z_limit: 200.0,
+};
+
+/// Longest device name in bytes, which fills a frame together with its terminating zero.
+pub const MAX_DEVICE_NAME_LEN: usize = 252;
+
+/// Velocity and acceleration ratios set when connecting, unless configured otherwise.
+pub const DEFAULT_PTP_COMMON_PARAMS: PtpCommonParams = PtpCommonParams {
+    velocity_ratio: 100.0,
+    acceleration_ratio: 100.0,
+};
+
+/// How requests are repeated when the arm does not answer them properly.
+///
+/// Only idempotent requests are repeated, see [`DobotMessage::is_idempotent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Pause before each repeated attempt.
+    pub backoff: Duration,
 }
 
-/// Describes the pose of robot arm.
-#[derive(Debug, Clone, PartialEq)]
-pub struct Pose {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-    pub r: f32,
-    pub j1: f32,
-    pub j2: f32,
-    pub j3: f32,
-    pub j4: f32,
+impl RetryPolicy {
+    /// Send every request only once.
+    pub const NEVER: Self = Self {
+        max_attempts: 1,
+        backoff: Duration::ZERO,
+    };
 }
 
-/// The Dobot robot arm controller type.
-pub struct Dobot {
-    serial: SerialPort,
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Duration::from_millis(10),
+        }
+    }
 }
 
-impl Dobot {
-    /// Create controller object from device file.
-    pub fn open() -> DobotResult<Self> {
-        let serial = SerialPort::open("/dev/ttyUSB0", 115200)?;
+/// Configures and connects a [`Dobot`].
+#[derive(Debug, Clone)]
+pub struct DobotBuilder {
+    port: String,
+    baud_rate: u32,
+    timeout: Duration,
+    retry_policy: RetryPolicy,
+    poll_interval: Duration,
+    rail: Option<bool>,
+    ptp_joint_params: PtpJointParams,
+    ptp_coordinate_params: PtpCoordinateParams,
+    ptp_jump_params: PtpJumpParams,
+    ptp_common_params: PtpCommonParams,
+}
+
+impl Default for DobotBuilder {
+    fn default() -> Self {
+        Self {
+            port: DEFAULT_PORT.to_string(),
+            baud_rate: DEFAULT_BAUD_RATE,
+            timeout: DEFAULT_TIMEOUT,
+            retry_policy: RetryPolicy::default(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            rail: None,
+            ptp_joint_params: DEFAULT_PTP_JOINT_PARAMS,
+            ptp_coordinate_params: DEFAULT_PTP_COORDINATE_PARAMS,
+            ptp_jump_params: DEFAULT_PTP_JUMP_PARAMS,
+            ptp_common_params: DEFAULT_PTP_COMMON_PARAMS,
+        }
+    }
+}
+
+impl DobotBuilder {
+    /// Set the serial port device file used by [`DobotBuilder::open`].
+    pub fn port(mut self, port: impl Into<String>) -> Self {
+        self.port = port.into();
+        self
+    }
+
+    /// Set the baud rate used by [`DobotBuilder::open`].
+    pub fn baud_rate(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    /// Set how long to wait for each response of the arm.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set how requests are repeated when the arm does not answer them.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Set how often waiting for a queued command reads the queue index.
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Configure whether a sliding rail is attached.
+    ///
+    /// By default the configuration stored in the arm is used.
+    pub fn rail(mut self, is_with_l: bool) -> Self {
+        self.rail = Some(is_with_l);
+        self
+    }
+
+    /// Set the joint velocities and accelerations of PTP moves, sent when connecting.
+    pub fn ptp_joint_params(mut self, params: PtpJointParams) -> Self {
+        self.ptp_joint_params = params;
+        self
+    }
+
+    /// Set the cartesian velocities and accelerations of PTP moves, sent when connecting.
+    pub fn ptp_coordinate_params(mut self, params: PtpCoordinateParams) -> Self {
+        self.ptp_coordinate_params = params;
+        self
+    }
+
+    /// Set the jump height and z limit of jump moves, sent when connecting.
+    pub fn ptp_jump_params(mut self, params: PtpJumpParams) -> Self {
+        self.ptp_jump_params = params;
+        self
+    }
+
+    /// Set the velocity and acceleration ratios of PTP moves, sent when connecting.
+    pub fn ptp_common_params(mut self, params: PtpCommonParams) -> Self {
+        self.ptp_common_params = params;
+        self
+    }
+
+    /// Connect to a Dobot attached to the configured serial port.
+    pub fn open(self) -> DobotResult<Dobot<SerialPort>> {
+        let serial = SerialPort::open(&self.port, self.baud_rate)?;
+        self.connect(serial)
+    }
+
+    /// Connect to a Dobot behind a TCP serial gateway such as ser2net.
+    pub fn connect_tcp<A: ToSocketAddrs>(self, addr: A) -> DobotResult<Dobot<TcpStream>> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        self.connect(stream)
+    }
 
-        let mut dobot = Self { serial };
+    /// Connect to a Dobot over an already opened transport.
+    pub fn connect<T: Transport>(self, transport: T) -> DobotResult<Dobot<T>> {
+        let mut dobot = Dobot::new(transport);
+        dobot.set_timeout(self.timeout);
+        dobot.set_retry_policy(self.retry_policy);
+        dobot.set_poll_interval(self.poll_interval);
 
         dobot.set_queued_cmd_start_exec()?;
         dobot.set_queued_cmd_clear()?;
-        dobot.set_ptp_joint_params(200.0, 200.0, 200.0, 200.0, 200.0, 200.0, 200.0, 200.0)?;
-        dobot.set_ptp_coordinate_params(200.0, 200.0)?;
-        dobot.set_ptp_jump_params(10.0, 200.0)?;
-        dobot.set_ptp_common_params(100.0, 100.0)?;
+        match self.rail {
+            Some(is_with_l) => dobot.set_device_with_l(is_with_l)?,
+            None => dobot.is_with_l = dobot.get_device_with_l()?,
+        }
+        dobot.set_ptp_joint_params(self.ptp_joint_params)?;
+        dobot.set_ptp_coordinate_params(self.ptp_coordinate_params)?;
+        dobot.set_ptp_jump_params(self.ptp_jump_params)?;
+        dobot.set_ptp_common_params(self.ptp_common_params)?;
         dobot.get_pose()?;
 
         Ok(dobot)
     }
+}
+
+/// The Dobot robot arm controller type.
+pub struct Dobot<T: Transport = SerialPort> {
+    transport: T,
+    decoder: FrameDecoder,
+    timeout: Duration,
+    retry_policy: RetryPolicy,
+    poll_interval: Duration,
+    is_with_l: bool,
+    is_lost_step_check: bool,
+}
+
+impl Dobot {
+    /// Create controller object from the default device file.
+    pub fn open() -> DobotResult<Self> {
+        Self::builder().open()
+    }
+
+    /// Configure a controller object before connecting it.
+    pub fn builder() -> DobotBuilder {
+        DobotBuilder::default()
+    }
+}
+
+impl<T: Transport> Dobot<T> {
+    /// Create controller object on top of a transport without configuring the arm.
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            decoder: FrameDecoder::new(),
+            timeout: DEFAULT_TIMEOUT,
+            retry_policy: RetryPolicy::default(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            is_with_l: false,
+            is_lost_step_check: false,
+        }
+    }
+
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// Set how long to wait for each response of the arm.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Set how often waiting for a queued command reads the queue index.
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.poll_interval = poll_interval;
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Check for lost steps after every PTP and arc move and at the end of every
+    /// streamed path, which raises a lost-step alarm when a motor deviates from
+    /// its encoder by more than the threshold.
+    pub fn set_lost_step_check(&mut self, is_lost_step_check: bool) {
+        self.is_lost_step_check = is_lost_step_check;
+    }
+
+    pub fn is_lost_step_check(&self) -> bool {
+        self.is_lost_step_check
+    }
+
+    /// Number of received bytes dropped because they were not part of a valid frame.
+    pub fn discarded_bytes(&self) -> usize {
+        self.decoder.discarded()
+    }
+
+    /// Send a typed command and decode its response.
+    pub fn execute<C: Command>(&mut self, command: &C) -> DobotResult<C::Response> {
+        let response_msg = self.send_command(command.to_message()?)?;
+        C::decode_response(&response_msg)
+    }
+
+    /// Put a command into the command queue of the arm.
+    ///
+    /// PTP and arc moves are followed by a lost-step check if checks are
+    /// enabled, the returned handle is then the one of the check.
+    pub fn queue<C: Command>(&mut self, command: C) -> DobotResult<WaitHandle<'_, T>> {
+        let mut index = self.execute(&Queued(command))?;
+        if self.is_lost_step_check && C::ID.is_lost_step_checked() {
+            index = self.execute(&Queued(SetLostStepCmd))?;
+        }
+        Ok(WaitHandle::new(self, index))
+    }
 
-    #[allow(clippy::too_many_arguments)]
     pub fn set_ptp_joint_params(
         &mut self,
-        v_x: f32,
-        v_y: f32,
-        v_z: f32,
-        v_r: f32,
-        a_x: f32,
-        a_y: f32,
-        a_z: f32,
-        a_r: f32,
-    ) -> DobotResult<WaitHandle<'_>> {
-        let params = [
-            v_x.to_le_bytes(),
-            v_y.to_le_bytes(),
-            v_z.to_le_bytes(),
-            v_r.to_le_bytes(),
-            a_x.to_le_bytes(),
-            a_y.to_le_bytes(),
-            a_z.to_le_bytes(),
-            a_r.to_le_bytes(),
-        ]
-        .iter()
-        .flatten()
-        .copied()
-        .collect();
-
-        let response_msg = self.send_command(DobotMessage::new(
-            CommandID::GetSetPtpJointParams,
-            true,
-            true,
-            params,
-        )?)?;
-        let index = u64::from_le_bytes(response_msg.params()[0..8].try_into().unwrap());
-
-        let handle = WaitHandle::new(self, index);
-        Ok(handle)
-    }
-
-    pub fn _set_cp_cmd(&mut self, x: f32, y: f32, z: f32) -> DobotResult<WaitHandle<'_>> {
-        let params = [0x01]
-            .iter()
-            .chain(
-                [x.to_le_bytes(), y.to_le_bytes(), z.to_le_bytes()]
-                    .iter()
-                    .flatten(),
-            )
-            .chain([0x00].iter())
-            .copied()
-            .collect();
-
-        let response_msg =
-            self.send_command(DobotMessage::new(CommandID::SetCpCmd, true, true, params)?)?;
-        let index = u64::from_le_bytes(response_msg.params()[0..8].try_into().unwrap());
-
-        let handle = WaitHandle::new(self, index);
-        Ok(handle)
+        params: PtpJointParams,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetPtpJointParams(params))
+    }
+
+    pub fn get_ptp_joint_params(&mut self) -> DobotResult<PtpJointParams> {
+        self.execute(&GetPtpJointParams)
     }
 
     pub fn set_ptp_coordinate_params(
         &mut self,
-        velocity: f32,
-        acceleration: f32,
-    ) -> DobotResult<WaitHandle<'_>> {
-        let params = [
-            velocity.to_le_bytes(),
-            velocity.to_le_bytes(),
-            acceleration.to_le_bytes(),
-            acceleration.to_le_bytes(),
-        ]
-        .iter()
-        .flatten()
-        .copied()
-        .collect();
-
-        let response_msg = self.send_command(DobotMessage::new(
-            CommandID::GetSetPtpCoordinateParams,
-            true,
-            true,
-            params,
-        )?)?;
-        let index = u64::from_le_bytes(response_msg.params()[0..8].try_into().unwrap());
-
-        let handle = WaitHandle::new(self, index);
-        Ok(handle)
-    }
-
-    pub fn set_ptp_jump_params(&mut self, jump: f32, limit: f32) -> DobotResult<WaitHandle<'_>> {
-        let params = [jump.to_le_bytes(), limit.to_le_bytes()]
-            .iter()
-            .flatten()
-            .copied()
-            .collect();
-
-        let response_msg = self.send_command(DobotMessage::new(
-            CommandID::GetSetPtpJumpParams,
-            true,
-            true,
-            params,
-        )?)?;
-        let index = u64::from_le_bytes(response_msg.params()[0..8].try_into().unwrap());
-
-        let handle = WaitHandle::new(self, index);
-        Ok(handle)
+        params: PtpCoordinateParams,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetPtpCoordinateParams(params))
+    }
+
+    pub fn get_ptp_coordinate_params(&mut self) -> DobotResult<PtpCoordinateParams> {
+        self.execute(&GetPtpCoordinateParams)
+    }
+
+    pub fn set_ptp_jump_params(&mut self, params: PtpJumpParams) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetPtpJumpParams(params))
+    }
+
+    pub fn get_ptp_jump_params(&mut self) -> DobotResult<PtpJumpParams> {
+        self.execute(&GetPtpJumpParams)
+    }
+
+    /// Set different lifting and lowering heights for jump moves.
+    pub fn set_ptp_jump2_params(
+        &mut self,
+        params: PtpJump2Params,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetPtpJump2Params(params))
+    }
+
+    pub fn get_ptp_jump2_params(&mut self) -> DobotResult<PtpJump2Params> {
+        self.execute(&GetPtpJump2Params)
     }
 
     pub fn set_ptp_common_params(
         &mut self,
-        velocity: f32,
-        acceleration: f32,
-    ) -> DobotResult<WaitHandle<'_>> {
-        let params = [velocity.to_le_bytes(), acceleration.to_le_bytes()]
-            .iter()
-            .flatten()
-            .copied()
-            .collect();
-
-        let response_msg = self.send_command(DobotMessage::new(
-            CommandID::GetSetPtpCommonParams,
-            true,
-            true,
-            params,
-        )?)?;
-        let index = u64::from_le_bytes(response_msg.params()[0..8].try_into().unwrap());
-
-        let handle = WaitHandle::new(self, index);
-        Ok(handle)
+        params: PtpCommonParams,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetPtpCommonParams(params))
+    }
+
+    pub fn get_ptp_common_params(&mut self) -> DobotResult<PtpCommonParams> {
+        self.execute(&GetPtpCommonParams)
     }
 
     pub fn set_ptp_cmd(
@@ -202,167 +389,764 @@ impl Dobot {
         z: f32,
         r: f32,
         mode: Mode,
-    ) -> DobotResult<WaitHandle<'_>> {
-        let request_msg = {
-            let params = [mode as u8]
-                .iter()
-                .chain(
-                    [
-                        x.to_le_bytes(),
-                        y.to_le_bytes(),
-                        z.to_le_bytes(),
-                        r.to_le_bytes(),
-                    ]
-                    .iter()
-                    .flatten(),
-                )
-                .copied()
-                .collect::<Vec<u8>>();
-            DobotMessage::new(CommandID::SetPtpCmd, true, true, params)?
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetPtpCmd(PtpCmd { mode, x, y, z, r }))
+    }
+
+    pub fn set_cp_params(&mut self, params: CpParams) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetCpParams(params))
+    }
+
+    pub fn get_cp_params(&mut self) -> DobotResult<CpParams> {
+        self.execute(&GetCpParams)
+    }
+
+    /// Queue a continuous path point.
+    ///
+    /// Points are not followed by lost-step checks, which would stop the arm
+    /// at each of them. Queue [`Dobot::set_lost_step_cmd`] after the last point
+    /// of a path, or stream it with [`Dobot::stream_cp`], which does.
+    pub fn set_cp_cmd(
+        &mut self,
+        mode: CpMode,
+        x: f32,
+        y: f32,
+        z: f32,
+        velocity: f32,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetCpCmd(CpCmd {
+            mode,
+            x,
+            y,
+            z,
+            velocity,
+        }))
+    }
+
+    /// Move along a continuous path with the laser switched on at the given power.
+    ///
+    /// Like [`Dobot::set_cp_cmd`], points are not followed by lost-step checks.
+    pub fn set_cp_le_cmd(
+        &mut self,
+        mode: CpMode,
+        x: f32,
+        y: f32,
+        z: f32,
+        power: f32,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetCpLeCmd(CpLeCmd {
+            mode,
+            x,
+            y,
+            z,
+            power,
+        }))
+    }
+
+    pub fn set_arc_params(&mut self, params: ArcParams) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetArcParams(params))
+    }
+
+    pub fn get_arc_params(&mut self) -> DobotResult<ArcParams> {
+        self.execute(&GetArcParams)
+    }
+
+    /// Move on the arc from the current position through `via` to `to`.
+    pub fn set_arc_cmd(
+        &mut self,
+        via: CartesianPoint,
+        to: CartesianPoint,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetArcCmd(ArcCmd { via, to }))
+    }
+
+    /// Start streaming continuous path points, see [`CpStream`].
+    pub fn cp_stream(&mut self, queue_depth: u64) -> CpStream<'_, T> {
+        CpStream::new(self, queue_depth)
+    }
+
+    /// Queue all points of a continuous path and return the handle of the last one,
+    /// or of the lost-step check after it.
+    pub fn stream_cp<I>(&mut self, points: I, queue_depth: u64) -> DobotResult<WaitHandle<'_, T>>
+    where
+        I: IntoIterator<Item = CpCmd>,
+    {
+        let mut stream = self.cp_stream(queue_depth);
+        for point in points {
+            stream.push(point)?;
+        }
+        stream.finish()
+    }
+
+    pub fn set_ptp_l_params(&mut self, params: PtpLParams) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetPtpLParams(params))
+    }
+
+    pub fn get_ptp_l_params(&mut self) -> DobotResult<PtpLParams> {
+        self.execute(&GetPtpLParams)
+    }
+
+    /// PTP move which also moves the sliding rail to position `l`.
+    pub fn set_ptp_with_l_cmd(
+        &mut self,
+        x: f32,
+        y: f32,
+        z: f32,
+        r: f32,
+        l: f32,
+        mode: Mode,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetPtpWithLCmd(PtpWithLCmd {
+            mode,
+            x,
+            y,
+            z,
+            r,
+            l,
+        }))
+    }
+
+    /// PTP move with the rail which switches outputs on its way.
+    pub fn set_ptp_po_with_l_cmd(
+        &mut self,
+        ptp: PtpWithLCmd,
+        outputs: Vec<ParallelOutputCmd>,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetPtpPoWithLCmd(PtpPoWithLCmd { ptp, outputs }))
+    }
+
+    /// Set the offset of the tool, which all cartesian poses refer to.
+    pub fn set_end_effector_params(
+        &mut self,
+        params: EndEffectorParams,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetEndEffectorParams(params))
+    }
+
+    pub fn get_end_effector_params(&mut self) -> DobotResult<EndEffectorParams> {
+        self.execute(&GetEndEffectorParams)
+    }
+
+    pub fn set_end_effector_suction_cup(&mut self, enable: bool) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetEndEffectorSuctionCup(EndEffectorSuctionCup {
+            is_ctrl_enabled: true,
+            is_sucked: enable,
+        }))
+    }
+
+    pub fn get_end_effector_suction_cup(&mut self) -> DobotResult<EndEffectorSuctionCup> {
+        self.execute(&GetEndEffectorSuctionCup)
+    }
+
+    /// Open or close the gripper, disabling control turns its pump off.
+    pub fn set_end_effector_gripper(
+        &mut self,
+        is_ctrl_enabled: bool,
+        is_gripped: bool,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetEndEffectorGripper(EndEffectorGripper {
+            is_ctrl_enabled,
+            is_gripped,
+        }))
+    }
+
+    pub fn get_end_effector_gripper(&mut self) -> DobotResult<EndEffectorGripper> {
+        self.execute(&GetEndEffectorGripper)
+    }
+
+    pub fn set_end_effector_laser(
+        &mut self,
+        is_ctrl_enabled: bool,
+        is_on: bool,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetEndEffectorLaser(EndEffectorLaser {
+            is_ctrl_enabled,
+            is_on,
+        }))
+    }
+
+    pub fn get_end_effector_laser(&mut self) -> DobotResult<EndEffectorLaser> {
+        self.execute(&GetEndEffectorLaser)
+    }
+
+    pub fn set_jog_joint_params(&mut self, params: JogJointParams) -> DobotResult<()> {
+        self.execute(&SetJogJointParams(params))
+    }
+
+    pub fn get_jog_joint_params(&mut self) -> DobotResult<JogJointParams> {
+        self.execute(&GetJogJointParams)
+    }
+
+    pub fn set_jog_coordinate_params(&mut self, params: JogCoordinateParams) -> DobotResult<()> {
+        self.execute(&SetJogCoordinateParams(params))
+    }
+
+    pub fn get_jog_coordinate_params(&mut self) -> DobotResult<JogCoordinateParams> {
+        self.execute(&GetJogCoordinateParams)
+    }
+
+    pub fn set_jog_common_params(&mut self, params: JogCommonParams) -> DobotResult<()> {
+        self.execute(&SetJogCommonParams(params))
+    }
+
+    pub fn get_jog_common_params(&mut self) -> DobotResult<JogCommonParams> {
+        self.execute(&GetJogCommonParams)
+    }
+
+    pub fn set_jog_l_params(&mut self, params: JogLParams) -> DobotResult<()> {
+        self.execute(&SetJogLParams(params))
+    }
+
+    pub fn get_jog_l_params(&mut self) -> DobotResult<JogLParams> {
+        self.execute(&GetJogLParams)
+    }
+
+    /// Start moving an axis until [`Dobot::stop_jog`] is called.
+    ///
+    /// Joint axes are moved when `is_joint` is set, cartesian axes otherwise.
+    pub fn jog(&mut self, is_joint: bool, cmd: JogCommand) -> DobotResult<()> {
+        self.execute(&SetJogCmd(JogCmd { is_joint, cmd }))
+    }
+
+    /// Stop the axis moved in jog mode.
+    pub fn stop_jog(&mut self) -> DobotResult<()> {
+        self.jog(false, JogCommand::Idle)
+    }
+
+    /// Set the speed of a stepper motor output, such as the conveyor belt.
+    pub fn set_e_motor(&mut self, e_motor: EMotor) -> DobotResult<()> {
+        self.execute(&SetEMotor(e_motor))
+    }
+
+    /// Select what an extended I/O pin is used for.
+    pub fn set_io_multiplexing(&mut self, address: u8, function: IoFunction) -> DobotResult<()> {
+        self.execute(&SetIoMultiplexing(IoMultiplexing { address, function }))
+    }
+
+    pub fn get_io_multiplexing(&mut self, address: u8) -> DobotResult<IoFunction> {
+        let request = IoMultiplexing {
+            address,
+            function: IoFunction::Dummy,
+        };
+        Ok(self.execute(&GetIoMultiplexing(request))?.function)
+    }
+
+    pub fn set_io_do(&mut self, address: u8, level: bool) -> DobotResult<()> {
+        self.execute(&SetIoDo(IoLevel { address, level }))
+    }
+
+    /// Level a digital output was last set to.
+    pub fn get_io_do(&mut self, address: u8) -> DobotResult<bool> {
+        let request = IoLevel {
+            address,
+            level: false,
         };
+        Ok(self.execute(&GetIoDo(request))?.level)
+    }
+
+    pub fn set_io_pwm(&mut self, address: u8, frequency: f32, duty_cycle: f32) -> DobotResult<()> {
+        self.execute(&SetIoPwm(IoPwm {
+            address,
+            frequency,
+            duty_cycle,
+        }))
+    }
+
+    pub fn get_io_pwm(&mut self, address: u8) -> DobotResult<IoPwm> {
+        let request = IoPwm {
+            address,
+            frequency: 0.0,
+            duty_cycle: 0.0,
+        };
+        self.execute(&GetIoPwm(request))
+    }
+
+    pub fn get_io_di(&mut self, address: u8) -> DobotResult<bool> {
+        let request = IoLevel {
+            address,
+            level: false,
+        };
+        Ok(self.execute(&GetIoDi(request))?.level)
+    }
+
+    /// Reading of an analog input, from 0 to 4095.
+    pub fn get_io_adc(&mut self, address: u8) -> DobotResult<u16> {
+        let request = IoAdc { address, value: 0 };
+        Ok(self.execute(&GetIoAdc(request))?.value)
+    }
+
+    pub fn set_color_sensor(
+        &mut self,
+        is_enabled: bool,
+        port: SensorPort,
+        version: SensorVersion,
+    ) -> DobotResult<()> {
+        self.execute(&SetColorSensor(SensorParams {
+            is_enabled,
+            port,
+            version,
+        }))
+    }
+
+    /// Read the colour sensor, which must have been enabled first.
+    pub fn get_color_sensor(&mut self) -> DobotResult<ColorSensorReading> {
+        self.execute(&GetColorSensor)
+    }
+
+    pub fn set_ir_switch(
+        &mut self,
+        is_enabled: bool,
+        port: SensorPort,
+        version: SensorVersion,
+    ) -> DobotResult<()> {
+        self.execute(&SetIrSwitch(SensorParams {
+            is_enabled,
+            port,
+            version,
+        }))
+    }
+
+    /// Whether the IR switch, which must have been enabled first, detects an object.
+    pub fn get_ir_switch(&mut self, port: SensorPort) -> DobotResult<bool> {
+        self.execute(&GetIrSwitch(port))
+    }
+
+    /// Set the deviation in degrees above which lost-step checks raise an alarm.
+    pub fn set_lost_step_params(&mut self, threshold: f32) -> DobotResult<()> {
+        self.execute(&SetLostStepParams(threshold))
+    }
 
-        let response_msg = self.send_command(request_msg)?;
-        let params = response_msg.params();
-        let index = u64::from_le_bytes(params[0..8].try_into().unwrap());
+    /// Check for lost steps once the moves queued so far are done.
+    pub fn set_lost_step_cmd(&mut self) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetLostStepCmd)
+    }
 
-        let handle = WaitHandle::new(self, index);
-        Ok(handle)
+    /// Hold the queue for the given time, to the millisecond and at most `u32::MAX` ms.
+    pub fn set_wait_cmd(&mut self, duration: Duration) -> DobotResult<WaitHandle<'_, T>> {
+        let timeout = u32::try_from(duration.as_millis())
+            .map_err(|_| DobotError::InvalidParams(CommandID::SetWaitCmd))?;
+        self.queue(SetWaitCmd(timeout))
     }
 
-    pub fn set_end_effector_suction_cup(&mut self, enable: bool) -> DobotResult<WaitHandle<'_>> {
-        let params = vec![0x01, enable as u8];
-        let response_msg = self.send_command(DobotMessage::new(
-            CommandID::GetSetEndEffectorSuctionCup,
-            true,
-            true,
-            params,
-        )?)?;
-        let index = u64::from_le_bytes(response_msg.params()[0..8].try_into().unwrap());
+    /// Hold the queue until a digital input of the extended I/O interface has the given level.
+    pub fn set_trig_level_cmd(
+        &mut self,
+        address: u8,
+        level: bool,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetTrigCmd(TrigCmd::level(address, level)))
+    }
 
-        let handle = WaitHandle::new(self, index);
-        Ok(handle)
+    /// Hold the queue until the reading of an analog input meets the condition.
+    pub fn set_trig_adc_cmd(
+        &mut self,
+        address: u8,
+        condition: AdcCondition,
+        threshold: u16,
+    ) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetTrigCmd(TrigCmd::adc(address, condition, threshold)))
     }
 
     pub fn set_queued_cmd_start_exec(&mut self) -> DobotResult<()> {
-        self.send_command(DobotMessage::new(
-            CommandID::SetQueuedCmdStartExec,
-            true,
-            false,
-            vec![],
-        )?)?;
-        Ok(())
+        self.execute(&SetQueuedCmdStartExec)
     }
 
-    pub fn _set_queued_cmd_stop_exec(&mut self) -> DobotResult<()> {
-        self.send_command(DobotMessage::new(
-            CommandID::SetQueuedCmdStopExec,
-            true,
-            false,
-            vec![],
-        )?)?;
-        Ok(())
+    /// Pause the queue once the current command is done, resumed by
+    /// [`Dobot::set_queued_cmd_start_exec`].
+    pub fn set_queued_cmd_stop_exec(&mut self) -> DobotResult<()> {
+        self.execute(&SetQueuedCmdStopExec)
     }
 
+    /// Stop the queue right away, the arm stops where it is.
+    pub fn set_queued_cmd_force_stop_exec(&mut self) -> DobotResult<()> {
+        self.execute(&SetQueuedCmdForceStopExec)
+    }
+
+    /// Drop the commands which are still queued.
     pub fn set_queued_cmd_clear(&mut self) -> DobotResult<()> {
-        self.send_command(DobotMessage::new(
-            CommandID::SetQueuedCmdClear,
-            true,
-            false,
-            vec![],
-        )?)?;
-        Ok(())
+        self.execute(&SetQueuedCmdClear)
+    }
+
+    /// Stop the arm, drop the queued commands and accept new ones again.
+    pub fn abort_queued_cmds(&mut self) -> DobotResult<()> {
+        self.set_queued_cmd_force_stop_exec()?;
+        self.set_queued_cmd_clear()?;
+        self.set_queued_cmd_start_exec()
+    }
+
+    /// Store the commands queued until [`Dobot::set_queued_cmd_stop_download`]
+    /// on the arm, which plays them offline `total_loop` times.
+    ///
+    /// `line_per_loop` counts every queued command, lost-step checks included.
+    /// Downloaded commands are not executed, so their wait handles never finish.
+    pub fn set_queued_cmd_start_download(
+        &mut self,
+        total_loop: u32,
+        line_per_loop: u32,
+    ) -> DobotResult<()> {
+        self.execute(&SetQueuedCmdStartDownload(DownloadParams {
+            total_loop,
+            line_per_loop,
+        }))
+    }
+
+    pub fn set_queued_cmd_stop_download(&mut self) -> DobotResult<()> {
+        self.execute(&SetQueuedCmdStopDownload)
     }
 
     pub fn get_queued_cmd_current_index(&mut self) -> DobotResult<u64> {
-        let request_msg =
-            DobotMessage::new(CommandID::SetQueuedCmdCurrentIndex, false, false, vec![])?;
-        let response_msg = self.send_command(request_msg)?;
-        let params = response_msg.params();
-        let index = u64::from_le_bytes(params[0..8].try_into().unwrap());
-        Ok(index)
+        self.execute(&GetQueuedCmdCurrentIndex)
+    }
+
+    /// Whether the queued command with the given index has been executed.
+    pub fn is_queued_cmd_done(&mut self, command_index: u64) -> DobotResult<bool> {
+        Ok(self.get_queued_cmd_current_index()? >= command_index)
     }
 
     /// Starts the calibration process.
-    pub fn set_home(&mut self) -> DobotResult<WaitHandle<'_>> {
-        let request_msg = DobotMessage::new(CommandID::SetHomeCmd, true, true, vec![])?;
-        let response_msg = self.send_command(request_msg)?;
-        let params = response_msg.params();
-        let index = u64::from_le_bytes(params[0..8].try_into().unwrap());
-        let handle = WaitHandle::new(self, index);
-        Ok(handle)
+    pub fn set_home(&mut self) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetHomeCmd)
     }
 
-    /// Get the current pose of robot.
-    pub fn get_pose(&mut self) -> DobotResult<Pose> {
-        let request_msg = DobotMessage::new(CommandID::GetPose, false, false, vec![])?;
-        let response_msg = self.send_command(request_msg)?;
+    /// Set the position the arm moves to at the end of homing.
+    pub fn set_home_params(&mut self, params: HomeParams) -> DobotResult<WaitHandle<'_, T>> {
+        self.queue(SetHomeParams(params))
+    }
 
-        let params = {
-            let params = response_msg.params();
-            if params.len() != 32 {
-                return Err(DobotError::Deserialize("message is truncated".into()));
-            }
-            params
-        };
+    pub fn get_home_params(&mut self) -> DobotResult<HomeParams> {
+        self.execute(&GetHomeParams)
+    }
 
-        let x = f32::from_le_bytes(params[0..4].try_into().unwrap());
-        let y = f32::from_le_bytes(params[4..8].try_into().unwrap());
-        let z = f32::from_le_bytes(params[8..12].try_into().unwrap());
-        let r = f32::from_le_bytes(params[12..16].try_into().unwrap());
-        let j1 = f32::from_le_bytes(params[16..20].try_into().unwrap());
-        let j2 = f32::from_le_bytes(params[20..24].try_into().unwrap());
-        let j3 = f32::from_le_bytes(params[24..28].try_into().unwrap());
-        let j4 = f32::from_le_bytes(params[28..32].try_into().unwrap());
+    pub fn set_hht_trig_mode(&mut self, mode: HhtTrigMode) -> DobotResult<()> {
+        self.execute(&SetHhtTrigMode(mode))
+    }
 
-        let pose = Pose {
-            x,
-            y,
-            z,
-            r,
-            j1,
-            j2,
-            j3,
-            j4,
-        };
+    pub fn get_hht_trig_mode(&mut self) -> DobotResult<HhtTrigMode> {
+        self.execute(&GetHhtTrigMode)
+    }
+
+    /// Enable the hand-hold teaching trigger read by [`Dobot::get_hht_trig_output`].
+    pub fn set_hht_trig_output_enabled(&mut self, is_enabled: bool) -> DobotResult<()> {
+        self.execute(&SetHhtTrigOutputEnabled(is_enabled))
+    }
+
+    pub fn get_hht_trig_output_enabled(&mut self) -> DobotResult<bool> {
+        self.execute(&GetHhtTrigOutputEnabled)
+    }
+
+    /// Whether hand-hold teaching triggered, such as by releasing the unlock
+    /// button, since the trigger was read last.
+    pub fn get_hht_trig_output(&mut self) -> DobotResult<bool> {
+        self.execute(&GetHhtTrigOutput)
+    }
+
+    pub fn get_device_sn(&mut self) -> DobotResult<String> {
+        self.execute(&GetDeviceSn)
+    }
+
+    pub fn get_device_name(&mut self) -> DobotResult<String> {
+        self.execute(&GetDeviceName)
+    }
+
+    /// Name the arm, with at most [`MAX_DEVICE_NAME_LEN`] bytes and no zero bytes.
+    pub fn set_device_name(&mut self, name: &str) -> DobotResult<()> {
+        if name.len() > MAX_DEVICE_NAME_LEN || name.contains('\0') {
+            return Err(DobotError::InvalidParams(CommandID::GetSetDeviceName));
+        }
+        self.execute(&SetDeviceName(name.to_string()))
+    }
+
+    pub fn get_device_version(&mut self) -> DobotResult<DeviceVersion> {
+        self.execute(&GetDeviceVersion)
+    }
+
+    /// Milliseconds since the arm was powered on.
+    pub fn get_device_time(&mut self) -> DobotResult<u32> {
+        self.execute(&GetDeviceTime)
+    }
+
+    /// Whether the arm is configured for a sliding rail.
+    pub fn get_device_with_l(&mut self) -> DobotResult<bool> {
+        self.execute(&GetDeviceWithL)
+    }
+
+    /// Configure whether a sliding rail is attached.
+    pub fn set_device_with_l(&mut self, is_with_l: bool) -> DobotResult<()> {
+        self.execute(&SetDeviceWithL(is_with_l))?;
+        self.is_with_l = is_with_l;
+        Ok(())
+    }
 
+    /// Whether a sliding rail is attached, as found when connecting.
+    pub fn is_with_l(&self) -> bool {
+        self.is_with_l
+    }
+
+    /// Get the current pose of robot, including the rail position if there is one.
+    pub fn get_pose(&mut self) -> DobotResult<Pose> {
+        let mut pose = self.execute(&GetPose)?;
+        if self.is_with_l {
+            pose.l = self.get_pose_l()?;
+        }
         Ok(pose)
     }
 
+    /// Get the position of the sliding rail.
+    pub fn get_pose_l(&mut self) -> DobotResult<f32> {
+        self.execute(&GetPoseL)
+    }
+
+    /// Get the alarms which are currently active.
+    pub fn get_alarms(&mut self) -> DobotResult<Alarms> {
+        self.execute(&GetAlarmsState)
+    }
+
+    /// Clear all alarms; alarms whose cause persists are raised again.
+    pub fn clear_alarms(&mut self) -> DobotResult<()> {
+        self.execute(&ClearAllAlarmsState)
+    }
+
     /// Send user-defined request to Dobot and obtain response.
+    ///
+    /// Idempotent requests are repeated according to the retry policy when
+    /// no matching response arrives in time.
     pub fn send_command(&mut self, request_msg: DobotMessage) -> DobotResult<DobotMessage> {
+        let max_attempts = match request_msg.is_idempotent() {
+            true => self.retry_policy.max_attempts.max(1),
+            false => 1,
+        };
+
+        let mut attempt = 1;
+        loop {
+            match self.exchange(&request_msg) {
+                Err(DobotError::Timeout | DobotError::ResponseMismatch { .. })
+                    if attempt < max_attempts =>
+                {
+                    attempt += 1;
+                    thread::sleep(self.retry_policy.backoff);
+                }
+                result => break result,
+            }
+        }
+    }
+
+    /// Write a request and read until the matching response arrives.
+    ///
+    /// Responses which do not match the request are late answers to earlier
+    /// requests and are skipped.
+    fn exchange(&mut self, request_msg: &DobotMessage) -> DobotResult<DobotMessage> {
+        // Bytes received before the request was sent cannot be part of its response.
+        self.decoder.clear();
+
         // send message
-        self.serial.write_all(request_msg.to_bytes().as_slice())?;
+        self.transport.write_all(request_msg.to_bytes().as_slice())?;
 
         // recive message
-        let response_msg = DobotMessage::from_async_reader(&mut self.serial)?;
+        let deadline = Instant::now() + self.timeout;
+        let mut mismatch = None;
+        loop {
+            match self.receive(deadline) {
+                Ok(response_msg)
+                    if response_msg.id() == request_msg.id()
+                        && response_msg.ctrl() == request_msg.ctrl() =>
+                {
+                    break Ok(response_msg)
+                }
+                Ok(response_msg) => {
+                    mismatch = Some(DobotError::ResponseMismatch {
+                        expected: request_msg.id(),
+                        expected_ctrl: request_msg.ctrl(),
+                        received: response_msg.id(),
+                        received_ctrl: response_msg.ctrl(),
+                    })
+                }
+                Err(DobotError::Timeout) => break Err(mismatch.unwrap_or(DobotError::Timeout)),
+                Err(error) => break Err(error),
+            }
+        }
+    }
+
+    /// Read the next valid message, giving up once the deadline has passed.
+    fn receive(&mut self, deadline: Instant) -> DobotResult<DobotMessage> {
+        let mut buffer = [0u8; 64];
+        loop {
+            if let Some(msg) = self.decoder.next_message() {
+                break Ok(msg);
+            }
 
-        Ok(response_msg)
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break Err(DobotError::Timeout);
+            }
+            self.transport.set_read_timeout(remaining)?;
+
+            match self.transport.read(&mut buffer) {
+                Ok(0) => break Err(IoError::from(ErrorKind::UnexpectedEof).into()),
+                Ok(len) => self.decoder.push(&buffer[..len]),
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted
+                    ) => {}
+                Err(error) => break Err(error.into()),
+            }
+        }
     }
 }
 
-pub struct WaitHandle<'a> {
+/// A command in the queue of the arm which can be waited for.
+///
+/// The handle borrows the [`Dobot`]; keep only its [`index`](Self::index) to
+/// check for completion later with [`Dobot::is_queued_cmd_done`].
+pub struct WaitHandle<'a, T: Transport> {
     command_index: u64,
-    dobot: &'a mut Dobot,
+    poll_interval: Duration,
+    dobot: &'a mut Dobot<T>,
 }
 
-impl<'a> WaitHandle<'a> {
-    pub(crate) fn new(dobot: &'a mut Dobot, command_index: u64) -> Self {
+impl<'a, T: Transport> WaitHandle<'a, T> {
+    pub(crate) fn new(dobot: &'a mut Dobot<T>, command_index: u64) -> Self {
         Self {
             command_index,
+            poll_interval: dobot.poll_interval,
             dobot,
         }
     }
 
-    pub fn wait(self) -> DobotResult<()> {
+    /// Queue index of the command.
+    pub fn index(&self) -> u64 {
+        self.command_index
+    }
+
+    /// Set how often the queue index is read while waiting.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Check once whether the command has been executed, without waiting.
+    pub fn is_done(&mut self) -> DobotResult<bool> {
+        self.dobot.is_queued_cmd_done(self.command_index)
+    }
+
+    /// Block until the command has been executed.
+    pub fn wait(mut self) -> DobotResult<()> {
+        while !self.is_done()? {
+            thread::sleep(self.poll_interval);
+        }
+        Ok(())
+    }
+
+    /// Block until the command has been executed or the timeout expires.
+    ///
+    /// Giving up does not remove the command from the queue.
+    pub fn wait_timeout(mut self, timeout: Duration) -> DobotResult<()> {
+        let deadline = Instant::now() + timeout;
         loop {
-            let current_index = self.dobot.get_queued_cmd_current_index()?;
-            if current_index == self.command_index {
+            if self.is_done()? {
                 break Ok(());
             }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break Err(DobotError::WaitTimeout {
+                    index: self.command_index,
+                });
+            }
+            thread::sleep(self.poll_interval.min(remaining));
         }
     }
 }
 
+/// Feeds continuous path points into the command queue of the arm.
+///
+/// At most `queue_depth` points wait in the queue at any time. This keeps the
+/// next points available to the planner of the arm, so that it does not stop
+/// between them, without overflowing the queue.
+pub struct CpStream<'a, T: Transport> {
+    dobot: &'a mut Dobot<T>,
+    queue_depth: u64,
+    last_index: u64,
+    current_index: u64,
+}
+
+impl<'a, T: Transport> CpStream<'a, T> {
+    pub(crate) fn new(dobot: &'a mut Dobot<T>, queue_depth: u64) -> Self {
+        Self {
+            dobot,
+            queue_depth: queue_depth.max(1),
+            last_index: 0,
+            current_index: 0,
+        }
+    }
+
+    /// Number of streamed points which have not been executed yet.
+    pub fn pending(&mut self) -> DobotResult<u64> {
+        self.current_index = self.dobot.get_queued_cmd_current_index()?;
+        Ok(self.last_index.saturating_sub(self.current_index))
+    }
+
+    /// Queue the next point, blocking while the queue is full.
+    pub fn push(&mut self, point: CpCmd) -> DobotResult<u64> {
+        // The cached index is only refreshed once the queue seems full.
+        while self.last_index.saturating_sub(self.current_index) >= self.queue_depth {
+            if self.pending()? >= self.queue_depth {
+                thread::sleep(self.dobot.poll_interval);
+            }
+        }
+        self.last_index = self.dobot.execute(&Queued(SetCpCmd(point)))?;
+        Ok(self.last_index)
+    }
+
+    /// Stop streaming and return the handle of the last queued point.
+    ///
+    /// If lost-step checks are enabled, a check is queued after the path and
+    /// the handle is the one of the check.
+    pub fn finish(self) -> DobotResult<WaitHandle<'a, T>> {
+        let mut index = self.last_index;
+        if self.dobot.is_lost_step_check {
+            index = self.dobot.execute(&Queued(SetLostStepCmd))?;
+        }
+        Ok(WaitHandle::new(self.dobot, index))
+    }
+}
+
+impl CommandID {
+    /// Whether the command is a move followed by a lost-step check, a PTP or an arc move.
+    ///
+    /// Continuous path points are checked at the end of the path instead, see
+    /// [`CpStream::finish`].
+    pub fn is_lost_step_checked(self) -> bool {
+        matches!(
+            self,
+            CommandID::SetPtpCmd
+                | CommandID::SetPtpWithLCmd
+                | CommandID::SetPtpPoCmd
+                | CommandID::SetPtpPoWithLCmd
+                | CommandID::SetSetArcCmd
+        )
+    }
+
+    /// Whether writing the command starts an action, so that repeating it repeats the action.
+    pub fn is_action(self) -> bool {
+        matches!(
+            self,
+            CommandID::SetHomeCmd
+                | CommandID::SetPtpCmd
+                | CommandID::SetPtpWithLCmd
+                | CommandID::SetPtpPoCmd
+                | CommandID::SetPtpPoWithLCmd
+                | CommandID::SetCpCmd
+                | CommandID::SetCpLeCmd
+                | CommandID::SetSetArcCmd
+                | CommandID::SetWaitCmd
+                | CommandID::SetTrigCmd
+                | CommandID::SetLostStepCmd
+                | CommandID::SetQueuedCmdStartDownload
+                | CommandID::SetQueuedCmdStopDownload
+        )
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromPrimitive)]
 pub enum CommandID {
     GetSetDeviceSn = 0,
@@ -435,3 +1219,305 @@ pub enum CommandID {
     SetQueuedCmdClear = 245,
     SetQueuedCmdCurrentIndex = 246,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dobot::{
+        emulator::Emulator,
+        transport::{pipe, PipeEnd},
+    };
+    use std::{io::Write, thread::JoinHandle};
+
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    /// Answer the requests arriving over a pipe with `respond`, which gets the
+    /// number of earlier requests, until the driver is dropped.
+    fn connect<F>(mut respond: F) -> (Dobot<PipeEnd>, JoinHandle<Vec<DobotMessage>>)
+    where
+        F: FnMut(usize, &DobotMessage) -> Vec<DobotMessage> + Send + 'static,
+    {
+        let (driver_end, mut arm_end) = pipe();
+        let arm = thread::spawn(move || {
+            let mut requests = Vec::new();
+            while let Ok(request) = DobotMessage::from_async_reader(&mut arm_end) {
+                for response in respond(requests.len(), &request) {
+                    arm_end.write_all(&response.to_bytes()).unwrap();
+                }
+                requests.push(request);
+            }
+            requests
+        });
+
+        let mut dobot = Dobot::new(driver_end);
+        dobot.set_timeout(TIMEOUT);
+        dobot.set_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            backoff: Duration::from_millis(1),
+        });
+        (dobot, arm)
+    }
+
+    fn get_pose() -> DobotMessage {
+        DobotMessage::new(CommandID::GetPose, false, false, vec![]).unwrap()
+    }
+
+    fn pose_response() -> DobotMessage {
+        DobotMessage::new(CommandID::GetPose, false, false, vec![0; 32]).unwrap()
+    }
+
+    fn alarms_response() -> DobotMessage {
+        DobotMessage::new(CommandID::GetAlarmsState, false, false, vec![0; 16]).unwrap()
+    }
+
+    #[test]
+    fn retries_a_request_which_timed_out() {
+        let (mut dobot, arm) = connect(|count, _| match count {
+            0 => vec![],
+            _ => vec![pose_response()],
+        });
+
+        let response = dobot.send_command(get_pose()).unwrap();
+        assert_eq!(response.id(), CommandID::GetPose);
+        drop(dobot);
+        assert_eq!(arm.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn gives_up_after_the_last_attempt() {
+        let (mut dobot, arm) = connect(|_, _| vec![]);
+
+        let result = dobot.send_command(get_pose());
+        assert!(matches!(result, Err(DobotError::Timeout)));
+        drop(dobot);
+        assert_eq!(arm.join().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn does_not_retry_queued_commands() {
+        let (mut dobot, arm) = connect(|_, _| vec![]);
+
+        let request = DobotMessage::new(CommandID::SetHomeCmd, true, true, vec![0; 4]).unwrap();
+        let result = dobot.send_command(request);
+        assert!(matches!(result, Err(DobotError::Timeout)));
+        drop(dobot);
+        assert_eq!(arm.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn skips_responses_to_other_requests() {
+        let (mut dobot, arm) = connect(|_, _| vec![alarms_response(), pose_response()]);
+
+        let response = dobot.send_command(get_pose()).unwrap();
+        assert_eq!(response.id(), CommandID::GetPose);
+        assert_eq!(response.params().len(), 32);
+        drop(dobot);
+        assert_eq!(arm.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn reports_a_mismatched_response() {
+        let (mut dobot, arm) = connect(|_, _| vec![alarms_response()]);
+
+        let result = dobot.send_command(get_pose());
+        match result {
+            Err(DobotError::ResponseMismatch {
+                expected,
+                expected_ctrl,
+                received,
+                received_ctrl,
+            }) => {
+                assert_eq!(expected, CommandID::GetPose);
+                assert_eq!(expected_ctrl, 0);
+                assert_eq!(received, CommandID::GetAlarmsState);
+                assert_eq!(received_ctrl, 0);
+            }
+            result => panic!("expected a response mismatch, got {:?}", result.map(|_| ())),
+        }
+        drop(dobot);
+        assert_eq!(arm.join().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn matches_the_ctrl_byte() {
+        // The response to a get does not answer a set of the same command.
+        let (mut dobot, arm) = connect(|_, request| {
+            let response = DobotMessage::new(request.id(), false, false, vec![]).unwrap();
+            vec![response]
+        });
+
+        let request = DobotMessage::new(CommandID::SetEMotor, true, false, vec![0; 6]).unwrap();
+        let result = dobot.send_command(request);
+        assert!(matches!(
+            result,
+            Err(DobotError::ResponseMismatch {
+                expected_ctrl: 1,
+                received_ctrl: 0,
+                ..
+            })
+        ));
+        drop(dobot);
+        arm.join().unwrap();
+    }
+
+    fn emulated() -> Dobot<PipeEnd> {
+        Dobot::builder()
+            .poll_interval(Duration::from_millis(5))
+            .connect(Emulator::new().spawn())
+            .unwrap()
+    }
+
+    #[test]
+    fn waits_for_a_queued_command() {
+        let mut dobot = emulated();
+        let handle = dobot.set_wait_cmd(Duration::from_millis(100)).unwrap();
+        let index = handle.index();
+        handle.wait_timeout(Duration::from_secs(2)).unwrap();
+        assert!(dobot.is_queued_cmd_done(index).unwrap());
+    }
+
+    #[test]
+    fn gives_up_waiting_after_the_timeout() {
+        let mut dobot = emulated();
+        let handle = dobot.set_wait_cmd(Duration::from_millis(500)).unwrap();
+        let index = handle.index();
+        let started = Instant::now();
+        let result = handle.wait_timeout(Duration::from_millis(50));
+        assert!(matches!(result, Err(DobotError::WaitTimeout { index: i }) if i == index));
+        assert!(started.elapsed() < Duration::from_millis(400));
+
+        // The command stays queued and is executed later.
+        assert!(!dobot.is_queued_cmd_done(index).unwrap());
+        dobot
+            .set_wait_cmd(Duration::ZERO)
+            .unwrap()
+            .wait_timeout(Duration::from_secs(2))
+            .unwrap();
+        assert!(dobot.is_queued_cmd_done(index).unwrap());
+    }
+
+    #[test]
+    fn earlier_commands_are_done_with_later_ones() {
+        let mut dobot = emulated();
+        let first = dobot
+            .set_wait_cmd(Duration::from_millis(50))
+            .unwrap()
+            .index();
+        dobot
+            .set_wait_cmd(Duration::from_millis(50))
+            .unwrap()
+            .wait_timeout(Duration::from_secs(2))
+            .unwrap();
+        assert!(dobot.is_queued_cmd_done(first).unwrap());
+    }
+
+    #[test]
+    fn waits_while_the_queue_is_stopped() {
+        let mut dobot = emulated();
+        dobot.set_queued_cmd_stop_exec().unwrap();
+        let index = dobot.set_wait_cmd(Duration::ZERO).unwrap().index();
+        let result = WaitHandle::new(&mut dobot, index).wait_timeout(Duration::from_millis(50));
+        assert!(matches!(result, Err(DobotError::WaitTimeout { .. })));
+
+        dobot.set_queued_cmd_start_exec().unwrap();
+        WaitHandle::new(&mut dobot, index)
+            .wait_timeout(Duration::from_secs(2))
+            .unwrap();
+    }
+
+    #[test]
+    fn keeps_at_most_the_queue_depth_of_cp_points_queued() {
+        let mut dobot = emulated();
+        let start = dobot.get_pose().unwrap();
+        let point = CpCmd {
+            mode: CpMode::Relative,
+            x: 5.0,
+            y: 0.0,
+            z: 0.0,
+            velocity: 100.0,
+        };
+
+        let mut stream = dobot.cp_stream(2);
+        for _ in 0..6 {
+            stream.push(point).unwrap();
+            assert!(stream.pending().unwrap() <= 2);
+        }
+        stream
+            .finish()
+            .unwrap()
+            .wait_timeout(Duration::from_secs(2))
+            .unwrap();
+
+        let pose = dobot.get_pose().unwrap();
+        assert!((pose.x - start.x - 30.0).abs() < 0.01, "x is {}", pose.x);
+    }
+
+    #[test]
+    fn round_trips_the_extended_io() {
+        let mut emulator = Emulator::new();
+        emulator.set_io_input(3, true);
+        emulator.set_io_adc(4, 1234);
+        let mut dobot = Dobot::builder().connect(emulator.spawn()).unwrap();
+
+        for (address, function) in [(2, IoFunction::DigitalOutput), (5, IoFunction::Pwm)] {
+            dobot.set_io_multiplexing(address, function).unwrap();
+            assert_eq!(dobot.get_io_multiplexing(address).unwrap(), function);
+        }
+        assert_eq!(dobot.get_io_multiplexing(6).unwrap(), IoFunction::Dummy);
+
+        dobot.set_io_do(2, true).unwrap();
+        assert!(dobot.get_io_do(2).unwrap());
+        dobot.set_io_do(2, false).unwrap();
+        assert!(!dobot.get_io_do(2).unwrap());
+
+        dobot.set_io_pwm(5, 1000.0, 25.0).unwrap();
+        let pwm = dobot.get_io_pwm(5).unwrap();
+        assert_eq!(
+            pwm,
+            IoPwm {
+                address: 5,
+                frequency: 1000.0,
+                duty_cycle: 25.0,
+            }
+        );
+
+        assert!(dobot.get_io_di(3).unwrap());
+        assert!(!dobot.get_io_di(7).unwrap());
+        assert_eq!(dobot.get_io_adc(4).unwrap(), 1234);
+    }
+
+    #[test]
+    fn names_the_arm() {
+        let mut dobot = emulated();
+        let name = "a".repeat(MAX_DEVICE_NAME_LEN);
+        dobot.set_device_name(&name).unwrap();
+        assert_eq!(dobot.get_device_name().unwrap(), name);
+
+        for name in ["a".repeat(MAX_DEVICE_NAME_LEN + 1), "arm\0two".to_string()] {
+            let result = dobot.set_device_name(&name);
+            assert!(matches!(
+                result,
+                Err(DobotError::InvalidParams(CommandID::GetSetDeviceName))
+            ));
+        }
+        assert_eq!(dobot.get_device_name().unwrap(), name);
+    }
+
+    #[test]
+    fn rejects_waits_longer_than_the_arm_can_count() {
+        let mut dobot = emulated();
+        let index = dobot.get_queued_cmd_current_index().unwrap();
+        let result = dobot.set_wait_cmd(Duration::from_millis(u64::from(u32::MAX) + 1));
+        assert!(matches!(
+            result,
+            Err(DobotError::InvalidParams(CommandID::SetWaitCmd))
+        ));
+        // Nothing was queued.
+        dobot
+            .set_wait_cmd(Duration::ZERO)
+            .unwrap()
+            .wait_timeout(Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(dobot.get_queued_cmd_current_index().unwrap(), index + 1);
+    }
+}
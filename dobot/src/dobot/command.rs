@@ -0,0 +1,1444 @@
+use crate::dobot::{
+    alarm::Alarms,
+    base::CommandID,
+    error::{Error as DobotError, Result as DobotResult},
+    message::DobotMessage,
+};
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use std::fmt;
+
+/// Reads little-endian values from the parameters of a message.
+pub struct ParamsReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ParamsReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /// Take the next `len` bytes.
+    pub fn take(&mut self, len: usize) -> DobotResult<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(DobotError::Deserialize("message is truncated".into()));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    /// Whether all bytes have been read.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Take all bytes which have not been read yet.
+    pub fn take_remaining(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.bytes)
+    }
+}
+
+/// Parameters which can be encoded into and decoded from a message.
+pub trait Params: Sized {
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    fn decode(reader: &mut ParamsReader) -> DobotResult<Self>;
+}
+
+/// Decode parameters from the bytes of a message.
+pub fn decode_params<P: Params>(bytes: &[u8]) -> DobotResult<P> {
+    P::decode(&mut ParamsReader::new(bytes))
+}
+
+impl Params for () {
+    fn encode(&self, _buf: &mut Vec<u8>) {}
+
+    fn decode(_reader: &mut ParamsReader) -> DobotResult<Self> {
+        Ok(())
+    }
+}
+
+impl Params for bool {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
+
+    fn decode(reader: &mut ParamsReader) -> DobotResult<Self> {
+        Ok(reader.take(1)?[0] != 0)
+    }
+}
+
+macro_rules! impl_params_for_number {
+    ($($ty:ty),*) => {
+        $(
+            impl Params for $ty {
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(reader: &mut ParamsReader) -> DobotResult<Self> {
+                    let bytes = reader.take(std::mem::size_of::<$ty>())?;
+                    Ok(<$ty>::from_le_bytes(bytes.try_into().unwrap()))
+                }
+            }
+        )*
+    };
+}
+
+impl_params_for_number!(u8, u16, u32, u64, i8, i32, f32);
+
+// A list takes all remaining bytes of the message.
+impl<P: Params> Params for Vec<P> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        for value in self {
+            value.encode(buf);
+        }
+    }
+
+    fn decode(reader: &mut ParamsReader) -> DobotResult<Self> {
+        let mut values = Vec::new();
+        while !reader.is_empty() {
+            values.push(P::decode(reader)?);
+        }
+        Ok(values)
+    }
+}
+
+// A string takes all remaining bytes of the message and is terminated with a zero.
+impl Params for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+        buf.push(0);
+    }
+
+    fn decode(reader: &mut ParamsReader) -> DobotResult<Self> {
+        let bytes = reader.take_remaining();
+        let end = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+}
+
+impl<P: Params, const N: usize> Params for [P; N] {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        for value in self {
+            value.encode(buf);
+        }
+    }
+
+    fn decode(reader: &mut ParamsReader) -> DobotResult<Self> {
+        let values = (0..N)
+            .map(|_| P::decode(reader))
+            .collect::<DobotResult<Vec<_>>>()?;
+        Ok(values.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+}
+
+/// Implements `Params` for an enum encoded as a single byte.
+macro_rules! impl_params_for_enum {
+    ($($ty:ty),*) => {
+        $(
+            impl Params for $ty {
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    buf.push(*self as u8);
+                }
+
+                fn decode(reader: &mut ParamsReader) -> DobotResult<Self> {
+                    let value = u8::decode(reader)?;
+                    <$ty>::from_u8(value).ok_or_else(|| {
+                        DobotError::Deserialize(format!(
+                            "invalid {} {}",
+                            stringify!($ty),
+                            value
+                        ))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+/// Defines a struct whose fields are encoded one after the other.
+macro_rules! params {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident {
+            $($(#[$field_meta:meta])* pub $field:ident: $ty:ty,)*
+        }
+    ) => {
+        $(#[$meta])*
+        pub struct $name {
+            $($(#[$field_meta])* pub $field: $ty,)*
+        }
+
+        impl Params for $name {
+            fn encode(&self, buf: &mut Vec<u8>) {
+                $(self.$field.encode(buf);)*
+            }
+
+            fn decode(reader: &mut ParamsReader) -> DobotResult<Self> {
+                Ok(Self {
+                    $($field: Params::decode(reader)?,)*
+                })
+            }
+        }
+    };
+}
+
+/// A request of the Dobot protocol together with the type of its response.
+///
+/// Commands are executed immediately by the arm. Wrap them in [`Queued`] to
+/// put them into the command queue instead.
+pub trait Command {
+    const ID: CommandID;
+    const RW: bool;
+    const IS_QUEUED: bool = false;
+    type Response: Params;
+
+    fn encode_params(&self, buf: &mut Vec<u8>);
+
+    fn to_message(&self) -> DobotResult<DobotMessage> {
+        let mut params = Vec::new();
+        self.encode_params(&mut params);
+        DobotMessage::new(Self::ID, Self::RW, Self::IS_QUEUED, params)
+    }
+
+    fn decode_response(msg: &DobotMessage) -> DobotResult<Self::Response> {
+        decode_params(msg.params())
+    }
+}
+
+/// Defines a command type, optionally wrapping its parameters.
+macro_rules! command {
+    ($(#[$meta:meta])* $name:ident => $id:ident, rw: $rw:literal, response: $response:ty;) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+        pub struct $name;
+
+        impl Command for $name {
+            const ID: CommandID = CommandID::$id;
+            const RW: bool = $rw;
+            type Response = $response;
+
+            fn encode_params(&self, _buf: &mut Vec<u8>) {}
+        }
+    };
+    ($(#[$meta:meta])* $name:ident($params:ty) => $id:ident, rw: $rw:literal, response: $response:ty;) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct $name(pub $params);
+
+        impl Command for $name {
+            const ID: CommandID = CommandID::$id;
+            const RW: bool = $rw;
+            type Response = $response;
+
+            fn encode_params(&self, buf: &mut Vec<u8>) {
+                self.0.encode(buf);
+            }
+        }
+    };
+}
+
+/// Puts a command into the command queue; the response is its queue index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Queued<C>(pub C);
+
+impl<C: Command> Command for Queued<C> {
+    const ID: CommandID = C::ID;
+    const RW: bool = C::RW;
+    const IS_QUEUED: bool = true;
+    type Response = u64;
+
+    fn encode_params(&self, buf: &mut Vec<u8>) {
+        self.0.encode_params(buf);
+    }
+}
+
+/// Defines the format to describe the robot pose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum Mode {
+    #[allow(non_camel_case_types)]
+    MODE_PTP_JUMP_XYZ = 0x00,
+    #[allow(non_camel_case_types)]
+    MODE_PTP_MOVJ_XYZ = 0x01,
+    #[allow(non_camel_case_types)]
+    MODE_PTP_MOVL_XYZ = 0x02,
+    #[allow(non_camel_case_types)]
+    MODE_PTP_JUMP_ANGLE = 0x03,
+    #[allow(non_camel_case_types)]
+    MODE_PTP_MOVJ_ANGLE = 0x04,
+    #[allow(non_camel_case_types)]
+    MODE_PTP_MOVL_ANGLE = 0x05,
+    #[allow(non_camel_case_types)]
+    MODE_PTP_MOVJ_INC = 0x06,
+    #[allow(non_camel_case_types)]
+    MODE_PTP_MOVL_INC = 0x07,
+    #[allow(non_camel_case_types)]
+    MODE_PTP_MOVJ_XYZ_INC = 0x08,
+    #[allow(non_camel_case_types)]
+    MODE_PTP_JUMP_MOVL_XYZ = 0x09,
+}
+
+impl_params_for_enum!(Mode);
+
+/// Describes the pose of robot arm.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pose {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub r: f32,
+    pub j1: f32,
+    pub j2: f32,
+    pub j3: f32,
+    pub j4: f32,
+    /// Position of the sliding rail, 0 without a rail.
+    pub l: f32,
+}
+
+// The rail position is not part of the message, it is read with `GetPoseL`.
+impl Params for Pose {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        [
+            self.x, self.y, self.z, self.r, self.j1, self.j2, self.j3, self.j4,
+        ]
+        .encode(buf);
+    }
+
+    fn decode(reader: &mut ParamsReader) -> DobotResult<Self> {
+        let [x, y, z, r, j1, j2, j3, j4] = <[f32; 8]>::decode(reader)?;
+        Ok(Self {
+            x,
+            y,
+            z,
+            r,
+            j1,
+            j2,
+            j3,
+            j4,
+            l: 0.0,
+        })
+    }
+}
+
+params! {
+    /// Velocity and acceleration of the individual joints in PTP mode.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct PtpJointParams {
+        pub velocity: [f32; 4],
+        pub acceleration: [f32; 4],
+    }
+}
+
+params! {
+    /// Velocity and acceleration of cartesian PTP moves.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct PtpCoordinateParams {
+        pub xyz_velocity: f32,
+        pub r_velocity: f32,
+        pub xyz_acceleration: f32,
+        pub r_acceleration: f32,
+    }
+}
+
+params! {
+    /// Position the arm moves to at the end of homing.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct HomeParams {
+        pub x: f32,
+        pub y: f32,
+        pub z: f32,
+        pub r: f32,
+    }
+}
+
+params! {
+    /// Lifting height and maximum height of PTP jump moves.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct PtpJumpParams {
+        pub jump_height: f32,
+        pub z_limit: f32,
+    }
+}
+
+params! {
+    /// Separate lifting and lowering heights of PTP jump moves.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct PtpJump2Params {
+        pub start_jump_height: f32,
+        pub end_jump_height: f32,
+        pub z_limit: f32,
+    }
+}
+
+params! {
+    /// Velocity and acceleration ratios applied to all PTP moves.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct PtpCommonParams {
+        pub velocity_ratio: f32,
+        pub acceleration_ratio: f32,
+    }
+}
+
+params! {
+    /// Target of a PTP move, interpreted according to the mode.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct PtpCmd {
+        pub mode: Mode,
+        pub x: f32,
+        pub y: f32,
+        pub z: f32,
+        pub r: f32,
+    }
+}
+
+/// How the coordinates of a continuous path point are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum CpMode {
+    /// Relative to the previous point.
+    Relative = 0x00,
+    Absolute = 0x01,
+}
+
+impl_params_for_enum!(CpMode);
+
+params! {
+    /// Planning parameters of continuous path moves.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct CpParams {
+        /// Maximum planned acceleration.
+        pub plan_acceleration: f32,
+        /// Maximum velocity at the junction of two segments.
+        pub junction_velocity: f32,
+        /// Maximum actual acceleration, or the period of the points in ms
+        /// when `is_real_time_track` is set.
+        pub acceleration_or_period: f32,
+        /// Whether points are sent in real time at a fixed period.
+        pub is_real_time_track: bool,
+    }
+}
+
+impl CpParams {
+    /// Parameters for paths which are planned as a whole.
+    pub fn planned(plan_acceleration: f32, junction_velocity: f32, acceleration: f32) -> Self {
+        Self {
+            plan_acceleration,
+            junction_velocity,
+            acceleration_or_period: acceleration,
+            is_real_time_track: false,
+        }
+    }
+
+    /// Parameters for points which are sent every `period` ms.
+    pub fn real_time(plan_acceleration: f32, junction_velocity: f32, period: f32) -> Self {
+        Self {
+            plan_acceleration,
+            junction_velocity,
+            acceleration_or_period: period,
+            is_real_time_track: true,
+        }
+    }
+}
+
+params! {
+    /// Velocity and acceleration of the sliding rail in PTP mode.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct PtpLParams {
+        pub velocity: f32,
+        pub acceleration: f32,
+    }
+}
+
+params! {
+    /// Target of a PTP move including the position of the sliding rail.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct PtpWithLCmd {
+        pub mode: Mode,
+        pub x: f32,
+        pub y: f32,
+        pub z: f32,
+        pub r: f32,
+        pub l: f32,
+    }
+}
+
+params! {
+    /// Switches an output once a move has covered a part of its way.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ParallelOutputCmd {
+        /// Percentage of the move after which the output is switched.
+        pub ratio: u8,
+        pub address: u16,
+        pub level: u8,
+    }
+}
+
+params! {
+    /// PTP move with the rail which switches outputs on its way.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct PtpPoWithLCmd {
+        pub ptp: PtpWithLCmd,
+        pub outputs: Vec<ParallelOutputCmd>,
+    }
+}
+
+params! {
+    /// Target of a continuous path move.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct CpCmd {
+        pub mode: CpMode,
+        pub x: f32,
+        pub y: f32,
+        pub z: f32,
+        pub velocity: f32,
+    }
+}
+
+params! {
+    /// Target of a continuous path move with the laser switched on.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct CpLeCmd {
+        pub mode: CpMode,
+        pub x: f32,
+        pub y: f32,
+        pub z: f32,
+        /// Laser power in percent.
+        pub power: f32,
+    }
+}
+
+params! {
+    /// Position of the tool in mm and rotation of the end effector in degrees.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct CartesianPoint {
+        pub x: f32,
+        pub y: f32,
+        pub z: f32,
+        pub r: f32,
+    }
+}
+
+params! {
+    /// Velocity and acceleration of arc moves.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct ArcParams {
+        pub xyz_velocity: f32,
+        pub r_velocity: f32,
+        pub xyz_acceleration: f32,
+        pub r_acceleration: f32,
+    }
+}
+
+params! {
+    /// Arc from the current position through a via point to an end point.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct ArcCmd {
+        pub via: CartesianPoint,
+        pub to: CartesianPoint,
+    }
+}
+
+params! {
+    /// Offset of the tool center point from the end of the forearm, in mm.
+    ///
+    /// The x bias extends the arm radially, the z bias points up.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct EndEffectorParams {
+        pub x_bias: f32,
+        pub y_bias: f32,
+        pub z_bias: f32,
+    }
+}
+
+params! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct EndEffectorSuctionCup {
+        pub is_ctrl_enabled: bool,
+        pub is_sucked: bool,
+    }
+}
+
+params! {
+    /// Pneumatic gripper, the pump is off unless control is enabled.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct EndEffectorGripper {
+        pub is_ctrl_enabled: bool,
+        pub is_gripped: bool,
+    }
+}
+
+params! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct EndEffectorLaser {
+        pub is_ctrl_enabled: bool,
+        pub is_on: bool,
+    }
+}
+
+params! {
+    /// Speed of one of the two stepper motor outputs, in pulses per second.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct EMotor {
+        pub index: u8,
+        pub is_enabled: bool,
+        pub speed: i32,
+    }
+}
+
+/// What an extended I/O pin is used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum IoFunction {
+    Dummy = 0,
+    DigitalOutput = 1,
+    Pwm = 2,
+    DigitalInput = 3,
+    Adc = 4,
+    DigitalInputPullUp = 5,
+    DigitalInputPullDown = 6,
+}
+
+impl_params_for_enum!(IoFunction);
+
+params! {
+    /// Function of an extended I/O pin, addressed from 1 to 20.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct IoMultiplexing {
+        pub address: u8,
+        pub function: IoFunction,
+    }
+}
+
+params! {
+    /// Level of a digital pin, high if `true`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct IoLevel {
+        pub address: u8,
+        pub level: bool,
+    }
+}
+
+params! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct IoPwm {
+        pub address: u8,
+        /// Frequency in Hz, from 10 Hz to 1 MHz.
+        pub frequency: f32,
+        /// Duty cycle in percent.
+        pub duty_cycle: f32,
+    }
+}
+
+params! {
+    /// Reading of an analog input, from 0 to 4095.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct IoAdc {
+        pub address: u8,
+        pub value: u16,
+    }
+}
+
+/// Extended I/O port a sensor of the sensor kit is plugged into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum SensorPort {
+    Gp1 = 0,
+    Gp2 = 1,
+    Gp4 = 2,
+    Gp5 = 3,
+}
+
+impl_params_for_enum!(SensorPort);
+
+/// Hardware version of a sensor of the sensor kit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum SensorVersion {
+    V1 = 0,
+    V2 = 1,
+}
+
+impl_params_for_enum!(SensorVersion);
+
+params! {
+    /// Enables the colour sensor or the IR switch of the sensor kit.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SensorParams {
+        pub is_enabled: bool,
+        pub port: SensorPort,
+        pub version: SensorVersion,
+    }
+}
+
+params! {
+    /// Colour seen by the colour sensor, each channel is set when it is detected.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ColorSensorReading {
+        pub red: u8,
+        pub green: u8,
+        pub blue: u8,
+    }
+}
+
+params! {
+    /// Firmware version of the arm.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct DeviceVersion {
+        pub major: u8,
+        pub minor: u8,
+        pub revision: u8,
+    }
+}
+
+impl fmt::Display for DeviceVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.revision)
+    }
+}
+
+command! {
+    GetDeviceSn => GetSetDeviceSn, rw: false, response: String;
+}
+
+command! {
+    GetDeviceName => GetSetDeviceName, rw: false, response: String;
+}
+
+command! {
+    SetDeviceName(String) => GetSetDeviceName, rw: true, response: ();
+}
+
+command! {
+    GetDeviceVersion => GetDeviceVersion, rw: false, response: DeviceVersion;
+}
+
+command! {
+    /// Milliseconds since the arm was powered on.
+    GetDeviceTime => GetDeviceTime, rw: false, response: u32;
+}
+
+command! {
+    /// Whether the arm is configured for a sliding rail.
+    GetDeviceWithL => GetDeviceWithL, rw: false, response: bool;
+}
+
+command! {
+    SetDeviceWithL(bool) => GetDeviceWithL, rw: true, response: ();
+}
+
+command! {
+    GetPose => GetPose, rw: false, response: Pose;
+}
+
+command! {
+    /// Position of the sliding rail in mm.
+    GetPoseL => GetPoseL, rw: false, response: f32;
+}
+
+command! {
+    SetHomeParams(HomeParams) => GetSetHomeParams, rw: true, response: ();
+}
+
+command! {
+    GetHomeParams => GetSetHomeParams, rw: false, response: HomeParams;
+}
+
+command! {
+    SetHomeCmd => SetHomeCmd, rw: true, response: ();
+}
+
+/// When hand-hold teaching triggers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum HhtTrigMode {
+    /// Once the unlock button is released.
+    KeyReleased = 0,
+    /// Periodically while the unlock button is pressed.
+    PeriodicInterval = 1,
+}
+
+impl_params_for_enum!(HhtTrigMode);
+
+command! {
+    SetHhtTrigMode(HhtTrigMode) => GetSetHHTTrigMode, rw: true, response: ();
+}
+
+command! {
+    GetHhtTrigMode => GetSetHHTTrigMode, rw: false, response: HhtTrigMode;
+}
+
+command! {
+    SetHhtTrigOutputEnabled(bool) => GetSetHHTTrigOutputEnabled, rw: true, response: ();
+}
+
+command! {
+    GetHhtTrigOutputEnabled => GetSetHHTTrigOutputEnabled, rw: false, response: bool;
+}
+
+command! {
+    /// Whether hand-hold teaching triggered since the output was read last.
+    GetHhtTrigOutput => GetHHTTrigOutput, rw: false, response: bool;
+}
+
+command! {
+    SetEndEffectorParams(EndEffectorParams) => GetSetEndEffectorParams, rw: true, response: ();
+}
+
+command! {
+    GetEndEffectorParams => GetSetEndEffectorParams, rw: false, response: EndEffectorParams;
+}
+
+command! {
+    SetEndEffectorSuctionCup(EndEffectorSuctionCup) => GetSetEndEffectorSuctionCup, rw: true, response: ();
+}
+
+command! {
+    GetEndEffectorSuctionCup => GetSetEndEffectorSuctionCup, rw: false, response: EndEffectorSuctionCup;
+}
+
+command! {
+    SetEndEffectorGripper(EndEffectorGripper) => GetSetEndEffectorGripper, rw: true, response: ();
+}
+
+command! {
+    GetEndEffectorGripper => GetSetEndEffectorGripper, rw: false, response: EndEffectorGripper;
+}
+
+command! {
+    SetEndEffectorLaser(EndEffectorLaser) => GetSetEndEffectorLaser, rw: true, response: ();
+}
+
+command! {
+    GetEndEffectorLaser => GetSetEndEffectorLaser, rw: false, response: EndEffectorLaser;
+}
+
+command! {
+    SetPtpJointParams(PtpJointParams) => GetSetPtpJointParams, rw: true, response: ();
+}
+
+command! {
+    GetPtpJointParams => GetSetPtpJointParams, rw: false, response: PtpJointParams;
+}
+
+command! {
+    SetPtpCoordinateParams(PtpCoordinateParams) => GetSetPtpCoordinateParams, rw: true, response: ();
+}
+
+command! {
+    GetPtpCoordinateParams => GetSetPtpCoordinateParams, rw: false, response: PtpCoordinateParams;
+}
+
+command! {
+    SetPtpJumpParams(PtpJumpParams) => GetSetPtpJumpParams, rw: true, response: ();
+}
+
+command! {
+    GetPtpJumpParams => GetSetPtpJumpParams, rw: false, response: PtpJumpParams;
+}
+
+command! {
+    SetPtpJump2Params(PtpJump2Params) => GetSetPtpJump2Params, rw: true, response: ();
+}
+
+command! {
+    GetPtpJump2Params => GetSetPtpJump2Params, rw: false, response: PtpJump2Params;
+}
+
+command! {
+    SetPtpCommonParams(PtpCommonParams) => GetSetPtpCommonParams, rw: true, response: ();
+}
+
+command! {
+    GetPtpCommonParams => GetSetPtpCommonParams, rw: false, response: PtpCommonParams;
+}
+
+command! {
+    SetPtpCmd(PtpCmd) => SetPtpCmd, rw: true, response: ();
+}
+
+command! {
+    SetPtpLParams(PtpLParams) => GetSetPtpLParams, rw: true, response: ();
+}
+
+command! {
+    GetPtpLParams => GetSetPtpLParams, rw: false, response: PtpLParams;
+}
+
+command! {
+    SetPtpWithLCmd(PtpWithLCmd) => SetPtpWithLCmd, rw: true, response: ();
+}
+
+command! {
+    SetPtpPoWithLCmd(PtpPoWithLCmd) => SetPtpPoWithLCmd, rw: true, response: ();
+}
+
+command! {
+    SetCpParams(CpParams) => GetSetCpParams, rw: true, response: ();
+}
+
+command! {
+    GetCpParams => GetSetCpParams, rw: false, response: CpParams;
+}
+
+command! {
+    SetCpCmd(CpCmd) => SetCpCmd, rw: true, response: ();
+}
+
+command! {
+    SetCpLeCmd(CpLeCmd) => SetCpLeCmd, rw: true, response: ();
+}
+
+command! {
+    SetArcParams(ArcParams) => GetSetArcParams, rw: true, response: ();
+}
+
+command! {
+    GetArcParams => GetSetArcParams, rw: false, response: ArcParams;
+}
+
+command! {
+    SetArcCmd(ArcCmd) => SetSetArcCmd, rw: true, response: ();
+}
+
+command! {
+    /// Sets the speed of a stepper motor, such as the one of the conveyor belt.
+    SetEMotor(EMotor) => SetEMotor, rw: true, response: ();
+}
+
+command! {
+    /// Deviation in degrees above which a lost-step check raises an alarm.
+    SetLostStepParams(f32) => SetLostStepParams, rw: true, response: ();
+}
+
+command! {
+    /// Compares the position of the motors with their encoders once the preceding moves are done.
+    SetLostStepCmd => SetLostStepCmd, rw: true, response: ();
+}
+
+command! {
+    /// Holds the queue for the given number of milliseconds.
+    SetWaitCmd(u32) => SetWaitCmd, rw: true, response: ();
+}
+
+/// What a trigger waits for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum TrigMode {
+    /// Level of a digital input.
+    Level = 0,
+    /// Reading of an analog input.
+    Adc = 1,
+}
+
+impl_params_for_enum!(TrigMode);
+
+/// How the reading of an analog input compares to the threshold of a trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum AdcCondition {
+    Less = 0,
+    LessOrEqual = 1,
+    GreaterOrEqual = 2,
+    Greater = 3,
+}
+
+impl AdcCondition {
+    pub fn is_met(self, value: u16, threshold: u16) -> bool {
+        match self {
+            AdcCondition::Less => value < threshold,
+            AdcCondition::LessOrEqual => value <= threshold,
+            AdcCondition::GreaterOrEqual => value >= threshold,
+            AdcCondition::Greater => value > threshold,
+        }
+    }
+}
+
+params! {
+    /// Condition on an extended I/O pin which holds the queue until it is met.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct TrigCmd {
+        pub address: u8,
+        pub mode: TrigMode,
+        /// 0 to wait for the level, 1 to wait for the other level, or an `AdcCondition`.
+        pub condition: u8,
+        /// Level 0 or 1 of a digital input, or threshold of an analog input from 0 to 4095.
+        pub threshold: u16,
+    }
+}
+
+impl TrigCmd {
+    /// Wait until a digital input has the given level.
+    pub fn level(address: u8, level: bool) -> Self {
+        Self {
+            address,
+            mode: TrigMode::Level,
+            condition: 0,
+            threshold: level.into(),
+        }
+    }
+
+    /// Wait until the reading of an analog input meets the condition.
+    pub fn adc(address: u8, condition: AdcCondition, threshold: u16) -> Self {
+        Self {
+            address,
+            mode: TrigMode::Adc,
+            condition: condition as u8,
+            threshold,
+        }
+    }
+}
+
+command! {
+    SetTrigCmd(TrigCmd) => SetTrigCmd, rw: true, response: ();
+}
+
+command! {
+    SetQueuedCmdStartExec => SetQueuedCmdStartExec, rw: true, response: ();
+}
+
+command! {
+    /// Stops executing the queue once the current command is done.
+    SetQueuedCmdStopExec => SetQueuedCmdStopExec, rw: true, response: ();
+}
+
+command! {
+    /// Stops executing the queue right away, aborting the current command.
+    SetQueuedCmdForceStopExec => SetQueuedCmdForceStopExec, rw: true, response: ();
+}
+
+params! {
+    /// Size of a program downloaded for offline playback.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct DownloadParams {
+        /// How many times the program is played.
+        pub total_loop: u32,
+        /// Number of queued commands in one loop of the program.
+        pub line_per_loop: u32,
+    }
+}
+
+command! {
+    /// Stores the following queued commands on the arm instead of executing them.
+    SetQueuedCmdStartDownload(DownloadParams) => SetQueuedCmdStartDownload, rw: true, response: ();
+}
+
+command! {
+    SetQueuedCmdStopDownload => SetQueuedCmdStopDownload, rw: true, response: ();
+}
+
+command! {
+    SetQueuedCmdClear => SetQueuedCmdClear, rw: true, response: ();
+}
+
+command! {
+    /// Index of the last command of the queue which has been executed.
+    GetQueuedCmdCurrentIndex => SetQueuedCmdCurrentIndex, rw: false, response: u64;
+}
+
+command! {
+    GetAlarmsState => GetAlarmsState, rw: false, response: Alarms;
+}
+
+command! {
+    ClearAllAlarmsState => ClearAllAlarmsState, rw: true, response: ();
+}
+
+params! {
+    /// Velocity and acceleration of the individual joints in jog mode.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct JogJointParams {
+        pub velocity: [f32; 4],
+        pub acceleration: [f32; 4],
+    }
+}
+
+params! {
+    /// Velocity and acceleration of the x, y, z and r axes in jog mode.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct JogCoordinateParams {
+        pub velocity: [f32; 4],
+        pub acceleration: [f32; 4],
+    }
+}
+
+params! {
+    /// Velocity and acceleration ratios applied to all jog moves.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct JogCommonParams {
+        pub velocity_ratio: f32,
+        pub acceleration_ratio: f32,
+    }
+}
+
+params! {
+    /// Velocity and acceleration of the sliding rail in jog mode.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct JogLParams {
+        pub velocity: f32,
+        pub acceleration: f32,
+    }
+}
+
+/// Axis and direction to move in jog mode.
+///
+/// Axes 1 to 4 are x, y, z and r in coordinate mode and the joints j1 to j4
+/// in joint mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum JogCommand {
+    Idle = 0,
+    Axis1Positive = 1,
+    Axis1Negative = 2,
+    Axis2Positive = 3,
+    Axis2Negative = 4,
+    Axis3Positive = 5,
+    Axis3Negative = 6,
+    Axis4Positive = 7,
+    Axis4Negative = 8,
+    RailPositive = 9,
+    RailNegative = 10,
+}
+
+impl_params_for_enum!(JogCommand);
+
+impl JogCommand {
+    /// Move the axis with the given index 0 to 3, or the rail with index 4.
+    pub fn new(axis: u8, is_positive: bool) -> Option<Self> {
+        if axis > 4 {
+            return None;
+        }
+        Self::from_u8(1 + 2 * axis + u8::from(!is_positive))
+    }
+
+    /// Index of the moved axis, 4 for the rail, and whether it moves in positive direction.
+    pub fn axis(self) -> Option<(usize, bool)> {
+        match self {
+            JogCommand::Idle => None,
+            cmd => {
+                let value = cmd as usize - 1;
+                Some((value / 2, value % 2 == 0))
+            }
+        }
+    }
+}
+
+params! {
+    /// Starts or stops moving an axis in jog mode.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct JogCmd {
+        pub is_joint: bool,
+        pub cmd: JogCommand,
+    }
+}
+
+command! {
+    SetJogJointParams(JogJointParams) => GetSetJogJointParams, rw: true, response: ();
+}
+
+command! {
+    GetJogJointParams => GetSetJogJointParams, rw: false, response: JogJointParams;
+}
+
+command! {
+    SetJogCoordinateParams(JogCoordinateParams) => GetSetJogCoordinateParams, rw: true, response: ();
+}
+
+command! {
+    GetJogCoordinateParams => GetSetJogCoordinateParams, rw: false, response: JogCoordinateParams;
+}
+
+command! {
+    SetJogCommonParams(JogCommonParams) => GetSetJogCommonParams, rw: true, response: ();
+}
+
+command! {
+    GetJogCommonParams => GetSetJogCommonParams, rw: false, response: JogCommonParams;
+}
+
+command! {
+    SetJogLParams(JogLParams) => GetSetJogLParams, rw: true, response: ();
+}
+
+command! {
+    GetJogLParams => GetSetJogLParams, rw: false, response: JogLParams;
+}
+
+command! {
+    SetJogCmd(JogCmd) => SetJogCmd, rw: true, response: ();
+}
+
+// The getters of the I/O pins send the parameters with the address set, the
+// arm responds with the other fields filled in.
+
+command! {
+    SetIoMultiplexing(IoMultiplexing) => GetSetIoMultiplexing, rw: true, response: ();
+}
+
+command! {
+    GetIoMultiplexing(IoMultiplexing) => GetSetIoMultiplexing, rw: false, response: IoMultiplexing;
+}
+
+command! {
+    SetIoDo(IoLevel) => GetSetIoDo, rw: true, response: ();
+}
+
+command! {
+    GetIoDo(IoLevel) => GetSetIoDo, rw: false, response: IoLevel;
+}
+
+command! {
+    SetIoPwm(IoPwm) => GetSetIoPwm, rw: true, response: ();
+}
+
+command! {
+    GetIoPwm(IoPwm) => GetSetIoPwm, rw: false, response: IoPwm;
+}
+
+command! {
+    GetIoDi(IoLevel) => GetIoDi, rw: false, response: IoLevel;
+}
+
+command! {
+    GetIoAdc(IoAdc) => GetIoAdc, rw: false, response: IoAdc;
+}
+
+command! {
+    SetColorSensor(SensorParams) => GetSetColorSensor, rw: true, response: ();
+}
+
+command! {
+    GetColorSensor => GetSetColorSensor, rw: false, response: ColorSensorReading;
+}
+
+command! {
+    SetIrSwitch(SensorParams) => GetSetIrSwitch, rw: true, response: ();
+}
+
+command! {
+    /// Whether the IR switch on the given port detects an object.
+    GetIrSwitch(SensorPort) => GetSetIrSwitch, rw: false, response: bool;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode<P: Params>(value: &P) -> Vec<u8> {
+        let mut buf = Vec::new();
+        value.encode(&mut buf);
+        buf
+    }
+
+    /// Encode and decode the value, returning its encoding.
+    fn round_trip<P: Params + PartialEq + fmt::Debug>(value: P) -> Vec<u8> {
+        let bytes = encode(&value);
+        let mut reader = ParamsReader::new(&bytes);
+        assert_eq!(P::decode(&mut reader).unwrap(), value);
+        assert!(reader.is_empty());
+        bytes
+    }
+
+    #[test]
+    fn encodes_numbers_little_endian() {
+        assert_eq!(round_trip(0x1234u16), [0x34, 0x12]);
+        assert_eq!(round_trip(-2i32), [0xfe, 0xff, 0xff, 0xff]);
+        assert_eq!(round_trip(1.0f32), [0x00, 0x00, 0x80, 0x3f]);
+        assert_eq!(round_trip(7u64), [7, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(round_trip(true), [1]);
+    }
+
+    #[test]
+    fn round_trips_structs() {
+        let e_motor = EMotor {
+            index: 0,
+            is_enabled: true,
+            speed: 10000,
+        };
+        assert_eq!(round_trip(e_motor), [0, 1, 0x10, 0x27, 0, 0]);
+
+        let ptp_cmd = PtpCmd {
+            mode: Mode::MODE_PTP_MOVL_XYZ,
+            x: 200.0,
+            y: -50.5,
+            z: 10.0,
+            r: 90.0,
+        };
+        let bytes = round_trip(ptp_cmd);
+        assert_eq!(bytes.len(), 17);
+        assert_eq!(bytes[0], 0x02);
+
+        round_trip(PtpJointParams {
+            velocity: [1.0, 2.0, 3.0, 4.0],
+            acceleration: [5.0, 6.0, 7.0, 8.0],
+        });
+    }
+
+    #[test]
+    fn appends_the_rail_position_to_ptp_moves() {
+        let ptp = PtpWithLCmd {
+            mode: Mode::MODE_PTP_MOVJ_XYZ,
+            x: 200.0,
+            y: 0.0,
+            z: 50.0,
+            r: 0.0,
+            l: 500.0,
+        };
+        let msg = Queued(SetPtpWithLCmd(ptp)).to_message().unwrap();
+        assert_eq!(msg.id() as u8, 86);
+        assert!(msg.rw() && msg.is_queued());
+        let bytes = round_trip(ptp);
+        assert_eq!(msg.params(), bytes);
+        assert_eq!(bytes.len(), 21);
+        assert_eq!(bytes[0], 0x01);
+        assert_eq!(bytes[17..], 500.0f32.to_le_bytes());
+
+        let output = ParallelOutputCmd {
+            ratio: 50,
+            address: 0x0102,
+            level: 1,
+        };
+        let bytes = round_trip(PtpPoWithLCmd {
+            ptp,
+            outputs: vec![output],
+        });
+        assert_eq!(bytes.len(), 25);
+        assert_eq!(bytes[21..], [50, 0x02, 0x01, 1]);
+    }
+
+    #[test]
+    fn decodes_sensor_kit_readings() {
+        let reading: ColorSensorReading = decode_params(&[0, 1, 0]).unwrap();
+        assert_eq!(
+            reading,
+            ColorSensorReading {
+                red: 0,
+                green: 1,
+                blue: 0,
+            }
+        );
+        assert!(decode_params::<ColorSensorReading>(&[0, 1]).is_err());
+        assert!(decode_params::<bool>(&[1]).unwrap());
+
+        let params = SensorParams {
+            is_enabled: true,
+            port: SensorPort::Gp4,
+            version: SensorVersion::V2,
+        };
+        assert_eq!(round_trip(params), [1, 2, 1]);
+        let msg = GetIrSwitch(SensorPort::Gp5).to_message().unwrap();
+        assert_eq!(msg.id(), CommandID::GetSetIrSwitch);
+        assert!(!msg.rw());
+        assert_eq!(msg.params(), [3]);
+    }
+
+    #[test]
+    fn rejects_truncated_params() {
+        let bytes = encode(&PtpJumpParams {
+            jump_height: 20.0,
+            z_limit: 100.0,
+        });
+        assert!(decode_params::<PtpJumpParams>(&bytes[..7]).is_err());
+        assert!(decode_params::<u32>(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_enum_values() {
+        assert!(matches!(
+            decode_params::<Mode>(&[0xff]),
+            Err(DobotError::Deserialize(_))
+        ));
+        assert_eq!(decode_params::<CpMode>(&[0x01]).unwrap(), CpMode::Absolute);
+    }
+
+    #[test]
+    fn terminates_strings_with_a_zero() {
+        assert_eq!(round_trip("Dobot".to_string()), b"Dobot\0");
+        assert_eq!(round_trip(String::new()), [0]);
+    }
+
+    #[test]
+    fn decodes_strings_up_to_the_zero() {
+        let name: String = decode_params(b"arm\0\xff\xff").unwrap();
+        assert_eq!(name, "arm");
+
+        // Not every firmware terminates the string.
+        let name: String = decode_params(b"arm").unwrap();
+        assert_eq!(name, "arm");
+    }
+
+    #[test]
+    fn decodes_lists_from_the_remaining_bytes() {
+        assert_eq!(round_trip(vec![1u16, 2, 3]), [1, 0, 2, 0, 3, 0]);
+        assert!(decode_params::<Vec<u16>>(&[1, 0, 2]).is_err());
+    }
+
+    #[test]
+    fn leaves_the_rail_out_of_poses() {
+        let pose = Pose {
+            x: 1.0,
+            y: 2.0,
+            z: 3.0,
+            r: 4.0,
+            j1: 5.0,
+            j2: 6.0,
+            j3: 7.0,
+            j4: 8.0,
+            l: 9.0,
+        };
+        let bytes = encode(&pose);
+        assert_eq!(bytes.len(), 32);
+        let decoded: Pose = decode_params(&bytes).unwrap();
+        assert_eq!(decoded, Pose { l: 0.0, ..pose });
+    }
+
+    #[test]
+    fn builds_messages_of_commands() {
+        let e_motor = EMotor {
+            index: 0,
+            is_enabled: true,
+            speed: 500,
+        };
+        let msg = SetEMotor(e_motor).to_message().unwrap();
+        assert_eq!(msg.id(), CommandID::SetEMotor);
+        assert!(msg.rw());
+        assert!(!msg.is_queued());
+        assert_eq!(msg.params(), encode(&e_motor));
+
+        let msg = Queued(SetEMotor(e_motor)).to_message().unwrap();
+        assert!(msg.is_queued());
+        assert_eq!(msg.params(), encode(&e_motor));
+
+        let msg = GetDeviceName.to_message().unwrap();
+        assert!(!msg.rw());
+        assert!(msg.params().is_empty());
+    }
+
+    #[test]
+    fn encodes_wait_and_trigger_commands() {
+        let msg = Queued(SetWaitCmd(1500)).to_message().unwrap();
+        assert_eq!(msg.id() as u8, 110);
+        assert!(msg.rw() && msg.is_queued());
+        assert_eq!(msg.params(), [0xdc, 0x05, 0, 0]);
+
+        let msg = Queued(SetTrigCmd(TrigCmd::level(3, true)))
+            .to_message()
+            .unwrap();
+        assert_eq!(msg.id() as u8, 120);
+        assert!(msg.rw() && msg.is_queued());
+        assert_eq!(msg.params(), [3, 0, 0, 1, 0]);
+
+        let trig = TrigCmd::adc(7, AdcCondition::GreaterOrEqual, 0x0800);
+        assert_eq!(round_trip(trig), [7, 1, 2, 0x00, 0x08]);
+    }
+
+    #[test]
+    fn jog_commands_name_their_axis_and_direction() {
+        for axis in 0..5 {
+            for is_positive in [true, false] {
+                let cmd = JogCommand::new(axis, is_positive).unwrap();
+                assert_eq!(cmd.axis(), Some((axis as usize, is_positive)));
+            }
+        }
+        assert_eq!(JogCommand::new(0, false), Some(JogCommand::Axis1Negative));
+        assert_eq!(JogCommand::new(4, true), Some(JogCommand::RailPositive));
+        assert_eq!(JogCommand::new(5, true), None);
+        assert_eq!(JogCommand::Idle.axis(), None);
+    }
+}
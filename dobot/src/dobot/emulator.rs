@@ -0,0 +1,1473 @@
+use crate::dobot::{
+    alarm::{Alarm, Alarms},
+    base::CommandID,
+    command::{
+        decode_params, AdcCondition, ArcCmd, ArcParams, CartesianPoint, ColorSensorReading, CpCmd,
+        CpLeCmd, CpMode, DeviceVersion, DownloadParams, EMotor, EndEffectorGripper,
+        EndEffectorLaser, EndEffectorParams, EndEffectorSuctionCup, HhtTrigMode, HomeParams, IoAdc,
+        IoFunction, IoLevel, IoMultiplexing, IoPwm, JogCmd, JogCommonParams, JogCoordinateParams,
+        JogJointParams, JogLParams, Mode, Params, Pose, PtpCmd, PtpCommonParams,
+        PtpCoordinateParams, PtpJointParams, PtpJumpParams, PtpLParams, PtpPoWithLCmd, PtpWithLCmd,
+        SensorParams, TrigCmd, TrigMode,
+    },
+    error::{Error as DobotError, Result as DobotResult},
+    message::DobotMessage,
+    transport::{pipe, PipeEnd},
+};
+use num_traits::FromPrimitive;
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, ToSocketAddrs},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Length of the rear arm in mm.
+const REAR_ARM_LENGTH: f32 = 135.0;
+/// Length of the forearm in mm.
+const FOREARM_LENGTH: f32 = 147.0;
+/// Horizontal distance from the wrist to the tool center point in mm.
+const END_EFFECTOR_OFFSET: f32 = 60.0;
+
+/// Joint angles the arm moves to when homing, unless a home position is set.
+const HOME_JOINTS: Joints = [0.0; 4];
+const HOMING_DURATION: Duration = Duration::from_secs(3);
+
+const SERIAL_NUMBER: &str = "EMULATOR";
+const FIRMWARE_VERSION: DeviceVersion = DeviceVersion {
+    major: 3,
+    minor: 7,
+    revision: 0,
+};
+
+const DEFAULT_JOINT_VELOCITY: f32 = 200.0;
+const DEFAULT_COORDINATE_VELOCITY: f32 = 200.0;
+const DEFAULT_RAIL_VELOCITY: f32 = 100.0;
+const DEFAULT_JOG_JOINT_VELOCITY: f32 = 15.0;
+const DEFAULT_JOG_COORDINATE_VELOCITY: f32 = 60.0;
+/// Number of straight segments an arc is approximated with.
+const ARC_SEGMENTS: usize = 32;
+const DEFAULT_JUMP_HEIGHT: f32 = 10.0;
+const DEFAULT_Z_LIMIT: f32 = 200.0;
+
+/// Angles of the four joints in degrees.
+type Joints = [f32; 4];
+
+/// Cartesian position x, y, z in mm and rotation r in degrees.
+type Cartesian = [f32; 4];
+
+fn forward_kinematics(joints: Joints) -> Cartesian {
+    let [j1, j2, j3, j4] = joints;
+    let radial = REAR_ARM_LENGTH * j2.to_radians().sin()
+        + FOREARM_LENGTH * j3.to_radians().cos()
+        + END_EFFECTOR_OFFSET;
+    let z = REAR_ARM_LENGTH * j2.to_radians().cos() - FOREARM_LENGTH * j3.to_radians().sin();
+    [
+        radial * j1.to_radians().cos(),
+        radial * j1.to_radians().sin(),
+        z,
+        j1 + j4,
+    ]
+}
+
+fn inverse_kinematics(cartesian: Cartesian) -> Option<Joints> {
+    let [x, y, z, r] = cartesian;
+    let j1 = y.atan2(x).to_degrees();
+    let radial = x.hypot(y) - END_EFFECTOR_OFFSET;
+    let distance = radial.hypot(z);
+
+    let cos_alpha = (REAR_ARM_LENGTH.powi(2) + distance.powi(2) - FOREARM_LENGTH.powi(2))
+        / (2.0 * REAR_ARM_LENGTH * distance);
+    if !(-1.0..=1.0).contains(&cos_alpha) {
+        return None;
+    }
+
+    // Elbow up: the rear arm is raised above the line from shoulder to wrist.
+    let rear_arm_elevation = z.atan2(radial) + cos_alpha.acos();
+    let j2 = 90.0 - rear_arm_elevation.to_degrees();
+    let forearm_radial = radial - REAR_ARM_LENGTH * rear_arm_elevation.cos();
+    let forearm_z = z - REAR_ARM_LENGTH * rear_arm_elevation.sin();
+    let j3 = (-forearm_z).atan2(forearm_radial).to_degrees();
+
+    Some([j1, j2, j3, r - j1])
+}
+
+type Vector = [f32; 3];
+
+fn sub(a: Vector, b: Vector) -> Vector {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: Vector, b: Vector) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Vector, b: Vector) -> Vector {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn scale(a: Vector, factor: f32) -> Vector {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+fn add(a: Vector, b: Vector) -> Vector {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+/// Points on the circle from `start` through `via` to `end`, excluding `start`.
+///
+/// Returns `None` when the points are on a line.
+fn arc_points(
+    start: Cartesian,
+    via: Cartesian,
+    end: Cartesian,
+    count: usize,
+) -> Option<Vec<Cartesian>> {
+    let position = |point: Cartesian| [point[0], point[1], point[2]];
+    let (a, b, c) = (position(start), position(via), position(end));
+    let u = sub(b, a);
+    let v = sub(c, a);
+    let normal = cross(u, v);
+    let normal_squared = dot(normal, normal);
+    if normal_squared < 1e-6 {
+        return None;
+    }
+
+    // Circumcenter of the triangle a, b, c.
+    let offset = add(
+        scale(cross(normal, u), dot(v, v)),
+        scale(cross(v, normal), dot(u, u)),
+    );
+    let center = add(a, scale(offset, 0.5 / normal_squared));
+
+    let radius_vector = sub(a, center);
+    let radius = dot(radius_vector, radius_vector).sqrt();
+    let e1 = scale(radius_vector, 1.0 / radius);
+    let e2 = cross(scale(normal, 1.0 / normal_squared.sqrt()), e1);
+    let angle = |point: Vector| {
+        let relative = sub(point, center);
+        let angle = dot(relative, e2).atan2(dot(relative, e1));
+        if angle < 0.0 {
+            angle + std::f32::consts::TAU
+        } else {
+            angle
+        }
+    };
+    let end_angle = angle(c);
+
+    let points = (1..=count)
+        .map(|step| {
+            let ratio = step as f32 / count as f32;
+            let theta = end_angle * ratio;
+            let [x, y, z] = add(
+                center,
+                add(
+                    scale(e1, radius * theta.cos()),
+                    scale(e2, radius * theta.sin()),
+                ),
+            );
+            [x, y, z, start[3] + (end[3] - start[3]) * ratio]
+        })
+        .collect();
+    Some(points)
+}
+
+fn lerp(from: [f32; 4], to: [f32; 4], ratio: f32) -> [f32; 4] {
+    let mut result = from;
+    for (value, (from, to)) in result.iter_mut().zip(from.iter().zip(to.iter())) {
+        *value = from + (to - from) * ratio;
+    }
+    result
+}
+
+/// Part of a motion between two joint configurations.
+#[derive(Debug, Clone)]
+struct Segment {
+    from: Joints,
+    to: Joints,
+    /// Whether the tool moves on a straight line rather than interpolating the joints.
+    is_linear: bool,
+    duration: Duration,
+}
+
+impl Segment {
+    fn position(&self, elapsed: Duration) -> Joints {
+        if elapsed >= self.duration {
+            return self.to;
+        }
+        let ratio = elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        if self.is_linear {
+            let from = forward_kinematics(self.from);
+            let to = forward_kinematics(self.to);
+            inverse_kinematics(lerp(from, to, ratio)).unwrap_or(self.to)
+        } else {
+            lerp(self.from, self.to, ratio)
+        }
+    }
+}
+
+/// A motion being executed by the emulated arm.
+#[derive(Debug, Clone)]
+struct Motion {
+    /// Queue index of the command, `None` for commands executed immediately.
+    index: Option<u64>,
+    segments: Vec<Segment>,
+    start: Instant,
+    /// Start and end position of the sliding rail, if it moves.
+    rail: Option<[f32; 2]>,
+}
+
+impl Motion {
+    fn duration(&self) -> Duration {
+        self.segments.iter().map(|segment| segment.duration).sum()
+    }
+
+    fn end(&self) -> Instant {
+        self.start + self.duration()
+    }
+
+    /// The rail moves at constant speed during the whole motion.
+    fn rail_position(&self, now: Instant) -> Option<f32> {
+        let [from, to] = self.rail?;
+        let elapsed = now.saturating_duration_since(self.start);
+        let ratio = (elapsed.as_secs_f32() / self.duration().as_secs_f32()).min(1.0);
+        Some(from + (to - from) * ratio)
+    }
+
+    fn position(&self, now: Instant) -> Joints {
+        let mut elapsed = now.saturating_duration_since(self.start);
+        for segment in &self.segments {
+            if elapsed < segment.duration {
+                return segment.position(elapsed);
+            }
+            elapsed -= segment.duration;
+        }
+        self.segments
+            .last()
+            .map(|segment| segment.to)
+            .unwrap_or_default()
+    }
+}
+
+/// Continuous motion of a single axis in jog mode.
+#[derive(Debug, Clone)]
+struct Jog {
+    from: Joints,
+    /// Index of the joint, or of the cartesian coordinate, which moves.
+    axis: usize,
+    is_joint: bool,
+    /// Signed velocity in degrees or mm per second.
+    velocity: f32,
+    start: Instant,
+}
+
+impl Jog {
+    /// Position at the given time, `None` once the tool leaves the reachable space.
+    fn position(&self, now: Instant) -> Option<Joints> {
+        let distance = self.velocity * now.saturating_duration_since(self.start).as_secs_f32();
+        if self.is_joint {
+            let mut joints = self.from;
+            joints[self.axis] += distance;
+            Some(joints)
+        } else {
+            let mut cartesian = forward_kinematics(self.from);
+            cartesian[self.axis] += distance;
+            inverse_kinematics(cartesian)
+        }
+    }
+}
+
+/// State of an extended I/O pin.
+#[derive(Debug, Clone, Copy)]
+struct IoPin {
+    function: IoFunction,
+    /// Level of a digital output, or of a digital input as set by [`Emulator::set_io_input`].
+    level: bool,
+    pwm: IoPwm,
+    adc: u16,
+}
+
+impl IoPin {
+    fn new(address: u8) -> Self {
+        Self {
+            function: IoFunction::Dummy,
+            level: false,
+            pwm: IoPwm {
+                address,
+                frequency: 0.0,
+                duty_cycle: 0.0,
+            },
+            adc: 0,
+        }
+    }
+}
+
+/// A software Dobot Magician which speaks the binary protocol.
+///
+/// Queued commands are executed in order once queue execution has been
+/// started, and PTP motions take time according to the configured PTP
+/// velocities. Accelerations are ignored. Targets out of reach raise an
+/// alarm instead of moving the arm. Parameters of commands which are not
+/// simulated are stored and returned when they are read back. Downloaded
+/// programs are played by [`Emulator::play_program`].
+pub struct Emulator {
+    joints: Joints,
+    queue: VecDeque<(u64, DobotMessage)>,
+    next_index: u64,
+    current_index: u64,
+    is_executing: bool,
+    motion: Option<Motion>,
+    jog: Option<Jog>,
+    rail: f32,
+    /// Rail position of the motion planned last, until the motion starts.
+    rail_target: Option<f32>,
+    is_suction_on: bool,
+    e_motor_speed: [i32; 2],
+    is_homed: bool,
+    /// When the emulated arm was powered on.
+    started: Instant,
+    alarms: Alarms,
+    io: HashMap<u8, IoPin>,
+    /// What the colour sensor and the IR switch see once they are enabled.
+    color: ColorSensorReading,
+    is_object_detected: bool,
+    /// Axis the next lost-step check finds lost steps on.
+    lost_steps: Option<u8>,
+    /// Whether hand-hold teaching triggered since the trigger was read.
+    is_hht_triggered: bool,
+    /// Size of the program being downloaded, until the download stops.
+    download: Option<DownloadParams>,
+    /// Queued commands of the downloaded program and how many times it is played.
+    program: Vec<DobotMessage>,
+    program_loops: u32,
+    params: HashMap<CommandID, Vec<u8>>,
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Emulator {
+    pub fn new() -> Self {
+        Self {
+            joints: HOME_JOINTS,
+            queue: VecDeque::new(),
+            next_index: 1,
+            current_index: 0,
+            is_executing: false,
+            motion: None,
+            jog: None,
+            rail: 0.0,
+            rail_target: None,
+            is_suction_on: false,
+            e_motor_speed: [0; 2],
+            is_homed: false,
+            started: Instant::now(),
+            alarms: Alarms::default(),
+            io: HashMap::new(),
+            color: ColorSensorReading {
+                red: 0,
+                green: 0,
+                blue: 0,
+            },
+            is_object_detected: false,
+            lost_steps: None,
+            is_hht_triggered: false,
+            download: None,
+            program: Vec::new(),
+            program_loops: 0,
+            params: HashMap::new(),
+        }
+    }
+
+    /// Run the emulator on a background thread and return the transport connected to it.
+    ///
+    /// The thread ends when the returned transport is dropped.
+    pub fn spawn(mut self) -> PipeEnd {
+        let (client, server) = pipe();
+        thread::spawn(move || self.serve(server));
+        client
+    }
+
+    /// Accept TCP connections one after the other and serve each of them.
+    ///
+    /// The state of the arm is kept between connections.
+    pub fn listen<A: ToSocketAddrs>(mut self, addr: A) -> DobotResult<()> {
+        let listener = TcpListener::bind(addr)?;
+        for stream in listener.incoming() {
+            let stream = stream?;
+            stream.set_nodelay(true)?;
+            self.serve(stream)?;
+        }
+        Ok(())
+    }
+
+    /// Answer the requests read from the stream until it is closed.
+    pub fn serve<S: Read + Write>(&mut self, mut stream: S) -> DobotResult<()> {
+        loop {
+            let request = match DobotMessage::from_async_reader(&mut stream) {
+                Ok(request) => request,
+                Err(DobotError::Io(error)) if error.kind() == ErrorKind::UnexpectedEof => {
+                    return Ok(())
+                }
+                Err(DobotError::Io(error)) => return Err(error.into()),
+                // Corrupted frames are dropped, just like the real arm does.
+                Err(_) => continue,
+            };
+            let response = match self.handle(&request) {
+                Ok(response) => response,
+                // Rejected requests are not answered, the client times out.
+                Err(DobotError::InvalidParams(_) | DobotError::Deserialize(_)) => continue,
+                Err(error) => return Err(error),
+            };
+            stream.write_all(response.to_bytes().as_slice())?;
+        }
+    }
+
+    /// Process a request and produce the response the arm would send.
+    ///
+    /// Motion parameters which cannot be decoded or with velocities the arm
+    /// cannot move at are rejected, without changing the state of the arm.
+    pub fn handle(&mut self, request: &DobotMessage) -> DobotResult<DobotMessage> {
+        check_velocities(request)?;
+        self.update();
+
+        let params = if request.is_queued() {
+            let index = self.next_index;
+            self.next_index += 1;
+            if self.download.is_some() {
+                self.program.push(request.clone());
+            } else {
+                self.queue.push_back((index, request.clone()));
+                self.update();
+            }
+            index.to_le_bytes().to_vec()
+        } else if request.rw() {
+            if let Some(segments) = self.execute(request) {
+                // Immediate motions are only accepted while the arm stands still.
+                if self.motion.is_none() && self.jog.is_none() {
+                    self.start_motion(None, segments, Instant::now());
+                }
+            }
+            self.rail_target = None;
+            // Resuming the queue starts its next command right away.
+            self.update();
+            vec![]
+        } else {
+            self.read(request)
+        };
+
+        DobotMessage::new(request.id(), request.rw(), request.is_queued(), params)
+    }
+
+    pub fn pose(&mut self) -> Pose {
+        self.update();
+        let [x, y, z, r] = forward_kinematics(self.joints);
+        let [j1, j2, j3, j4] = self.joints;
+        Pose {
+            x,
+            y,
+            z,
+            r,
+            j1,
+            j2,
+            j3,
+            j4,
+            l: self.rail,
+        }
+    }
+
+    pub fn is_suction_on(&self) -> bool {
+        self.is_suction_on
+    }
+
+    pub fn e_motor_speed(&self, index: usize) -> i32 {
+        self.e_motor_speed[index]
+    }
+
+    pub fn is_homed(&self) -> bool {
+        self.is_homed
+    }
+
+    pub fn alarms(&self) -> Alarms {
+        self.alarms
+    }
+
+    /// Drive a digital input of the extended I/O interface.
+    pub fn set_io_input(&mut self, address: u8, level: bool) {
+        self.io_pin(address).level = level;
+    }
+
+    /// Set the reading of an analog input of the extended I/O interface.
+    pub fn set_io_adc(&mut self, address: u8, value: u16) {
+        self.io_pin(address).adc = value;
+    }
+
+    /// Set the colour the colour sensor sees.
+    pub fn set_color(&mut self, color: ColorSensorReading) {
+        self.color = color;
+    }
+
+    /// Set whether there is an object in front of the IR switch.
+    pub fn set_object_detected(&mut self, is_object_detected: bool) {
+        self.is_object_detected = is_object_detected;
+    }
+
+    /// Make the next lost-step check find lost steps on the given axis, from 0 to 3.
+    pub fn lose_steps(&mut self, axis: u8) {
+        self.lost_steps = Some(axis);
+    }
+
+    /// Release the unlock button on the forearm, which triggers hand-hold
+    /// teaching once its output is enabled.
+    pub fn release_unlock_button(&mut self) {
+        if self.stored::<bool>(CommandID::GetSetHHTTrigOutputEnabled) == Some(true) {
+            self.is_hht_triggered = true;
+        }
+    }
+
+    /// Queue the downloaded program as often as it was downloaded for, like the
+    /// key on the base of the arm does.
+    pub fn play_program(&mut self) {
+        for _ in 0..self.program_loops {
+            for request in &self.program {
+                self.queue.push_back((self.next_index, request.clone()));
+                self.next_index += 1;
+            }
+        }
+        self.is_executing = true;
+        self.update();
+    }
+
+    fn io_pin(&mut self, address: u8) -> &mut IoPin {
+        self.io
+            .entry(address)
+            .or_insert_with(|| IoPin::new(address))
+    }
+
+    /// Advance the queue and the running motion to the current time.
+    fn update(&mut self) {
+        let now = Instant::now();
+        if let Some(jog) = &self.jog {
+            // The queue waits while an axis is jogged.
+            match jog.position(now) {
+                Some(joints) => self.joints = joints,
+                None => {
+                    self.alarms.insert(Alarm::MoveInverseLimit);
+                    self.jog = None;
+                }
+            }
+            return;
+        }
+
+        let mut start = now;
+        loop {
+            if let Some(motion) = &self.motion {
+                let end = motion.end();
+                if end > now {
+                    self.joints = motion.position(now);
+                    self.rail = motion.rail_position(now).unwrap_or(self.rail);
+                    return;
+                }
+                self.joints = motion.position(end);
+                self.rail = motion.rail_position(end).unwrap_or(self.rail);
+                if let Some(index) = motion.index {
+                    self.current_index = index;
+                }
+                self.motion = None;
+                start = end;
+            }
+
+            if !self.is_executing {
+                return;
+            }
+            let Some((index, request)) = self.queue.pop_front() else {
+                return;
+            };
+            if request.id() == CommandID::SetTrigCmd && !self.is_triggered(&request) {
+                self.queue.push_front((index, request));
+                return;
+            }
+            match self.execute(&request) {
+                Some(segments) => self.start_motion(Some(index), segments, start),
+                None => self.current_index = index,
+            }
+        }
+    }
+
+    fn start_motion(&mut self, index: Option<u64>, segments: Vec<Segment>, start: Instant) {
+        let rail = self.rail_target.take().map(|to| [self.rail, to]);
+        self.motion = Some(Motion {
+            index,
+            segments,
+            start,
+            rail,
+        });
+    }
+
+    /// Apply a write command and return the motion it starts, if any.
+    fn execute(&mut self, request: &DobotMessage) -> Option<Vec<Segment>> {
+        let params = request.params();
+        match request.id() {
+            CommandID::SetQueuedCmdStartExec => self.is_executing = true,
+            CommandID::SetQueuedCmdStopExec => self.is_executing = false,
+            CommandID::SetQueuedCmdForceStopExec => {
+                // The motion was advanced to now before the request was handled.
+                // The arm counts the stopped command as executed.
+                self.is_executing = false;
+                if let Some(index) = self.motion.take().and_then(|motion| motion.index) {
+                    self.current_index = index;
+                }
+                self.rail_target = None;
+            }
+            CommandID::SetQueuedCmdClear => self.queue.clear(),
+            CommandID::SetQueuedCmdStartDownload => {
+                self.download = Some(decode_params(params).ok()?);
+                self.program.clear();
+            }
+            CommandID::SetQueuedCmdStopDownload => {
+                if let Some(download) = self.download.take() {
+                    self.program_loops = download.total_loop;
+                    // Commands beyond the announced length are not stored.
+                    self.program.truncate(download.line_per_loop as usize);
+                }
+            }
+            CommandID::SetHomeCmd => {
+                self.is_homed = true;
+                let to = self
+                    .stored::<HomeParams>(CommandID::GetSetHomeParams)
+                    .and_then(|home| inverse_kinematics([home.x, home.y, home.z, home.r]))
+                    .unwrap_or(HOME_JOINTS);
+                return Some(vec![Segment {
+                    from: self.joints,
+                    to,
+                    is_linear: false,
+                    duration: HOMING_DURATION,
+                }]);
+            }
+            CommandID::SetPtpCmd => {
+                let cmd: PtpCmd = decode_params(params).ok()?;
+                let segments = self.plan_ptp(cmd.mode, [cmd.x, cmd.y, cmd.z, cmd.r]);
+                if segments.is_none() {
+                    self.alarms.insert(Alarm::PlanInverseLimit);
+                }
+                return segments;
+            }
+            CommandID::SetCpCmd => {
+                let cmd: CpCmd = decode_params(params).ok()?;
+                return self.plan_cp(cmd.mode, [cmd.x, cmd.y, cmd.z], cmd.velocity);
+            }
+            CommandID::SetCpLeCmd => {
+                let cmd: CpLeCmd = decode_params(params).ok()?;
+                return self.plan_cp(cmd.mode, [cmd.x, cmd.y, cmd.z], 0.0);
+            }
+            CommandID::SetSetArcCmd => {
+                let cmd: ArcCmd = decode_params(params).ok()?;
+                return self.plan_arc(cmd.via, cmd.to);
+            }
+            CommandID::SetWaitCmd => {
+                let timeout: u32 = decode_params(params).ok()?;
+                return Some(vec![Segment {
+                    from: self.joints,
+                    to: self.joints,
+                    is_linear: false,
+                    duration: Duration::from_millis(timeout.into()),
+                }]);
+            }
+            // The queue only executes triggers once they are met.
+            CommandID::SetTrigCmd => (),
+            CommandID::ClearAllAlarmsState => self.alarms.clear(),
+            CommandID::SetLostStepCmd => {
+                if let Some(alarm) = self
+                    .lost_steps
+                    .take()
+                    .and_then(|axis| Alarm::from_u8(Alarm::LostStepAxis1.code() + axis))
+                {
+                    self.alarms.insert(alarm);
+                }
+            }
+            CommandID::SetJogCmd => {
+                let cmd: JogCmd = decode_params(params).ok()?;
+                self.jog = self.plan_jog(cmd);
+            }
+            CommandID::SetPtpWithLCmd => {
+                let cmd: PtpWithLCmd = decode_params(params).ok()?;
+                return self.plan_ptp_with_l(cmd);
+            }
+            CommandID::SetPtpPoWithLCmd => {
+                // The outputs are not emulated.
+                let cmd: PtpPoWithLCmd = decode_params(params).ok()?;
+                return self.plan_ptp_with_l(cmd.ptp);
+            }
+            CommandID::GetSetEndEffectorSuctionCup => {
+                let suction_cup: EndEffectorSuctionCup = decode_params(params).ok()?;
+                self.is_suction_on = suction_cup.is_ctrl_enabled && suction_cup.is_sucked;
+            }
+            CommandID::GetSetIoMultiplexing => {
+                let multiplexing: IoMultiplexing = decode_params(params).ok()?;
+                self.io_pin(multiplexing.address).function = multiplexing.function;
+            }
+            CommandID::GetSetIoDo => {
+                let output: IoLevel = decode_params(params).ok()?;
+                self.io_pin(output.address).level = output.level;
+            }
+            CommandID::GetSetIoPwm => {
+                let pwm: IoPwm = decode_params(params).ok()?;
+                self.io_pin(pwm.address).pwm = pwm;
+            }
+            CommandID::SetEMotor => {
+                let e_motor: EMotor = decode_params(params).ok()?;
+                if let Some(speed) = self.e_motor_speed.get_mut(e_motor.index as usize) {
+                    *speed = if e_motor.is_enabled { e_motor.speed } else { 0 };
+                }
+            }
+            id => {
+                self.params.insert(id, params.to_vec());
+            }
+        }
+        None
+    }
+
+    /// Whether the condition of a trigger is met by the extended I/O inputs.
+    fn is_triggered(&self, request: &DobotMessage) -> bool {
+        let Ok(cmd) = decode_params::<TrigCmd>(request.params()) else {
+            // Invalid triggers do not hold the queue.
+            return true;
+        };
+        let pin = self.io.get(&cmd.address);
+        match cmd.mode {
+            TrigMode::Level => {
+                let level = pin.is_some_and(|pin| pin.level);
+                (level == (cmd.threshold != 0)) == (cmd.condition == 0)
+            }
+            TrigMode::Adc => {
+                let value = pin.map_or(0, |pin| pin.adc);
+                AdcCondition::from_u8(cmd.condition)
+                    .is_none_or(|condition| condition.is_met(value, cmd.threshold))
+            }
+        }
+    }
+
+    /// Produce the parameters of a read command.
+    fn read(&mut self, request: &DobotMessage) -> Vec<u8> {
+        let id = request.id();
+        // Requests for an I/O pin start with its address.
+        let address = request.params().first().copied().unwrap_or_default();
+        let mut params = Vec::new();
+        match id {
+            CommandID::GetPose => self.pose().encode(&mut params),
+            CommandID::GetPoseL => self.rail.encode(&mut params),
+            CommandID::GetSetDeviceSn => SERIAL_NUMBER.to_string().encode(&mut params),
+            CommandID::GetDeviceVersion => FIRMWARE_VERSION.encode(&mut params),
+            CommandID::GetDeviceTime => {
+                (self.started.elapsed().as_millis() as u32).encode(&mut params)
+            }
+            CommandID::GetDeviceWithL => {
+                self.stored::<bool>(id).unwrap_or(false).encode(&mut params)
+            }
+            CommandID::GetSetHHTTrigMode => self
+                .stored(id)
+                .unwrap_or(HhtTrigMode::KeyReleased)
+                .encode(&mut params),
+            CommandID::GetSetHHTTrigOutputEnabled => {
+                self.stored::<bool>(id).unwrap_or(false).encode(&mut params)
+            }
+            CommandID::GetHHTTrigOutput => {
+                std::mem::take(&mut self.is_hht_triggered).encode(&mut params)
+            }
+            CommandID::SetQueuedCmdCurrentIndex => self.current_index.encode(&mut params),
+            CommandID::GetAlarmsState => self.alarms.encode(&mut params),
+            CommandID::GetSetEndEffectorSuctionCup => EndEffectorSuctionCup {
+                is_ctrl_enabled: true,
+                is_sucked: self.is_suction_on,
+            }
+            .encode(&mut params),
+            // The kinematics always use the default offset.
+            CommandID::GetSetEndEffectorParams => self
+                .stored(id)
+                .unwrap_or(EndEffectorParams {
+                    x_bias: END_EFFECTOR_OFFSET,
+                    y_bias: 0.0,
+                    z_bias: 0.0,
+                })
+                .encode(&mut params),
+            CommandID::GetSetEndEffectorGripper => self
+                .stored(id)
+                .unwrap_or(EndEffectorGripper {
+                    is_ctrl_enabled: false,
+                    is_gripped: false,
+                })
+                .encode(&mut params),
+            CommandID::GetSetEndEffectorLaser => self
+                .stored(id)
+                .unwrap_or(EndEffectorLaser {
+                    is_ctrl_enabled: false,
+                    is_on: false,
+                })
+                .encode(&mut params),
+            CommandID::GetSetIoMultiplexing => IoMultiplexing {
+                address,
+                function: self.io_pin(address).function,
+            }
+            .encode(&mut params),
+            CommandID::GetSetIoDo | CommandID::GetIoDi => IoLevel {
+                address,
+                level: self.io_pin(address).level,
+            }
+            .encode(&mut params),
+            CommandID::GetSetIoPwm => self.io_pin(address).pwm.encode(&mut params),
+            CommandID::GetIoAdc => IoAdc {
+                address,
+                value: self.io_pin(address).adc,
+            }
+            .encode(&mut params),
+            // Disabled sensors do not see anything, whatever port they are read on.
+            CommandID::GetSetColorSensor => match self.is_sensor_enabled(id) {
+                true => self.color,
+                false => ColorSensorReading {
+                    red: 0,
+                    green: 0,
+                    blue: 0,
+                },
+            }
+            .encode(&mut params),
+            CommandID::GetSetIrSwitch => {
+                (self.is_sensor_enabled(id) && self.is_object_detected).encode(&mut params)
+            }
+            id => params = self.params.get(&id).cloned().unwrap_or_default(),
+        }
+        params
+    }
+
+    /// Parameters last written with the given command.
+    fn stored<P: Params>(&self, id: CommandID) -> Option<P> {
+        decode_params(self.params.get(&id)?).ok()
+    }
+
+    fn is_sensor_enabled(&self, id: CommandID) -> bool {
+        self.stored::<SensorParams>(id)
+            .is_some_and(|sensor| sensor.is_enabled)
+    }
+
+    fn velocity_ratio(&self) -> f32 {
+        self.stored::<PtpCommonParams>(CommandID::GetSetPtpCommonParams)
+            .map_or(1.0, |params| params.velocity_ratio / 100.0)
+    }
+
+    fn joint_segment(&self, from: Joints, to: Joints) -> Option<Segment> {
+        let velocity = self
+            .stored::<PtpJointParams>(CommandID::GetSetPtpJointParams)
+            .map_or(DEFAULT_JOINT_VELOCITY, |params| params.velocity[0])
+            * self.velocity_ratio();
+        let angle = from
+            .iter()
+            .zip(to.iter())
+            .map(|(from, to)| (to - from).abs())
+            .fold(0.0, f32::max);
+        Some(Segment {
+            from,
+            to,
+            is_linear: false,
+            duration: duration(angle, velocity)?,
+        })
+    }
+
+    fn linear_segment(&self, from: Joints, to: Joints) -> Option<Segment> {
+        let velocity = self
+            .stored::<PtpCoordinateParams>(CommandID::GetSetPtpCoordinateParams)
+            .map_or(DEFAULT_COORDINATE_VELOCITY, |params| params.xyz_velocity)
+            * self.velocity_ratio();
+        straight_segment(from, to, velocity)
+    }
+
+    /// Plan a continuous path move, the velocity of the point overrides the PTP velocity.
+    ///
+    /// Segments are not blended, the arm reaches every point.
+    fn plan_cp(&mut self, mode: CpMode, values: [f32; 3], velocity: f32) -> Option<Vec<Segment>> {
+        let from = self.joints;
+        let mut target = forward_kinematics(from);
+        for (value, new) in target.iter_mut().zip(values.iter()) {
+            match mode {
+                CpMode::Relative => *value += new,
+                CpMode::Absolute => *value = *new,
+            }
+        }
+        let Some(to) = inverse_kinematics(target) else {
+            self.alarms.insert(Alarm::PlanInverseLimit);
+            return None;
+        };
+        let segment = match velocity > 0.0 {
+            true => straight_segment(from, to, velocity)?,
+            false => self.linear_segment(from, to)?,
+        };
+        Some(vec![segment])
+    }
+
+    /// Plan the segments of a `SetPtpCmd` in the given PTP mode.
+    fn plan_ptp(&self, mode: Mode, values: [f32; 4]) -> Option<Vec<Segment>> {
+        let from = self.joints;
+        let cartesian_increment = || {
+            let mut target = forward_kinematics(from);
+            for (value, increment) in target.iter_mut().zip(values.iter()) {
+                *value += increment;
+            }
+            inverse_kinematics(target)
+        };
+        let joint_increment = || {
+            let mut target = from;
+            for (value, increment) in target.iter_mut().zip(values.iter()) {
+                *value += increment;
+            }
+            target
+        };
+
+        let segments = match mode {
+            Mode::MODE_PTP_JUMP_XYZ => self.jump(from, inverse_kinematics(values)?, false)?,
+            Mode::MODE_PTP_MOVJ_XYZ => vec![self.joint_segment(from, inverse_kinematics(values)?)?],
+            Mode::MODE_PTP_MOVL_XYZ => {
+                vec![self.linear_segment(from, inverse_kinematics(values)?)?]
+            }
+            Mode::MODE_PTP_JUMP_ANGLE => self.jump(from, values, false)?,
+            Mode::MODE_PTP_MOVJ_ANGLE => vec![self.joint_segment(from, values)?],
+            Mode::MODE_PTP_MOVL_ANGLE => vec![self.linear_segment(from, values)?],
+            Mode::MODE_PTP_MOVJ_INC => vec![self.joint_segment(from, joint_increment())?],
+            Mode::MODE_PTP_MOVL_INC => vec![self.linear_segment(from, cartesian_increment()?)?],
+            Mode::MODE_PTP_MOVJ_XYZ_INC => {
+                vec![self.joint_segment(from, cartesian_increment()?)?]
+            }
+            Mode::MODE_PTP_JUMP_MOVL_XYZ => self.jump(from, inverse_kinematics(values)?, true)?,
+        };
+        Some(segments)
+    }
+
+    /// Plan an arc through `via` to `to` as a chain of short straight segments.
+    fn plan_arc(&mut self, via: CartesianPoint, to: CartesianPoint) -> Option<Vec<Segment>> {
+        let velocity = self
+            .stored::<ArcParams>(CommandID::GetSetArcParams)
+            .map_or(DEFAULT_COORDINATE_VELOCITY, |params| params.xyz_velocity);
+        let point = |p: CartesianPoint| [p.x, p.y, p.z, p.r];
+        let start = forward_kinematics(self.joints);
+        let Some(points) = arc_points(start, point(via), point(to), ARC_SEGMENTS) else {
+            self.alarms.insert(Alarm::PlanArcInput);
+            return None;
+        };
+
+        let mut segments = Vec::with_capacity(points.len());
+        let mut from = self.joints;
+        for point in points {
+            let Some(joints) = inverse_kinematics(point) else {
+                self.alarms.insert(Alarm::PlanInverseLimit);
+                return None;
+            };
+            segments.push(straight_segment(from, joints, velocity)?);
+            from = joints;
+        }
+        Some(segments)
+    }
+
+    /// Start moving an axis, `None` to stop.
+    fn plan_jog(&self, cmd: JogCmd) -> Option<Jog> {
+        // Jogging is ignored while a motion is running, jogging the rail is not emulated.
+        let (axis, is_positive) = cmd.cmd.axis().filter(|(axis, _)| *axis < 4)?;
+        if self.motion.is_some() {
+            return None;
+        }
+
+        let velocity = if cmd.is_joint {
+            self.stored::<JogJointParams>(CommandID::GetSetJogJointParams)
+                .map_or(DEFAULT_JOG_JOINT_VELOCITY, |params| params.velocity[axis])
+        } else {
+            self.stored::<JogCoordinateParams>(CommandID::GetSetJogCoordinateParams)
+                .map_or(DEFAULT_JOG_COORDINATE_VELOCITY, |params| {
+                    params.velocity[axis]
+                })
+        };
+        let ratio = self
+            .stored::<JogCommonParams>(CommandID::GetSetJogCommonParams)
+            .map_or(1.0, |params| params.velocity_ratio / 100.0);
+        let direction = if is_positive { 1.0 } else { -1.0 };
+
+        Some(Jog {
+            from: self.joints,
+            axis,
+            is_joint: cmd.is_joint,
+            velocity: direction * velocity * ratio,
+            start: Instant::now(),
+        })
+    }
+
+    /// Plan a PTP move, holding the arm at its target until the rail arrives.
+    fn plan_ptp_with_l(&mut self, cmd: PtpWithLCmd) -> Option<Vec<Segment>> {
+        let Some(mut segments) = self.plan_ptp(cmd.mode, [cmd.x, cmd.y, cmd.z, cmd.r]) else {
+            self.alarms.insert(Alarm::PlanInverseLimit);
+            return None;
+        };
+
+        let velocity = self
+            .stored::<PtpLParams>(CommandID::GetSetPtpLParams)
+            .map_or(DEFAULT_RAIL_VELOCITY, |params| params.velocity)
+            * self.velocity_ratio();
+        let rail_duration = duration((cmd.l - self.rail).abs(), velocity)?;
+        let arm_duration: Duration = segments.iter().map(|segment| segment.duration).sum();
+        if rail_duration > arm_duration {
+            let target = segments.last().map_or(self.joints, |segment| segment.to);
+            segments.push(Segment {
+                from: target,
+                to: target,
+                is_linear: false,
+                duration: rail_duration - arm_duration,
+            });
+        }
+        self.rail_target = Some(cmd.l);
+        Some(segments)
+    }
+
+    /// Lift the tool, travel above the target and lower it again.
+    fn jump(&self, from: Joints, to: Joints, is_linear: bool) -> Option<Vec<Segment>> {
+        let (jump_height, z_limit) = self
+            .stored::<PtpJumpParams>(CommandID::GetSetPtpJumpParams)
+            .map_or((DEFAULT_JUMP_HEIGHT, DEFAULT_Z_LIMIT), |params| {
+                (params.jump_height, params.z_limit)
+            });
+
+        let start = forward_kinematics(from);
+        let end = forward_kinematics(to);
+        let highest = start[2].max(end[2]);
+        let top = (highest + jump_height).min(z_limit).max(highest);
+
+        let lifted = inverse_kinematics([start[0], start[1], top, start[3]]).unwrap_or(from);
+        let above = inverse_kinematics([end[0], end[1], top, end[3]]).unwrap_or(to);
+        let travel = if is_linear {
+            self.linear_segment(lifted, above)?
+        } else {
+            self.joint_segment(lifted, above)?
+        };
+        Some(vec![
+            self.linear_segment(from, lifted)?,
+            travel,
+            self.linear_segment(above, to)?,
+        ])
+    }
+}
+
+/// A move on a straight line at the given velocity in mm per second.
+fn straight_segment(from: Joints, to: Joints, velocity: f32) -> Option<Segment> {
+    let [x1, y1, z1, _] = forward_kinematics(from);
+    let [x2, y2, z2, _] = forward_kinematics(to);
+    let distance = ((x2 - x1).powi(2) + (y2 - y1).powi(2) + (z2 - z1).powi(2)).sqrt();
+    Some(Segment {
+        from,
+        to,
+        is_linear: true,
+        duration: duration(distance, velocity)?,
+    })
+}
+
+/// Time to cover the distance, `None` if it is too long to represent.
+fn duration(distance: f32, velocity: f32) -> Option<Duration> {
+    Duration::try_from_secs_f32(distance / velocity).ok()
+}
+
+/// Reject motion parameters whose velocities are not finite and positive.
+fn check_velocities(request: &DobotMessage) -> DobotResult<()> {
+    if !request.rw() {
+        return Ok(());
+    }
+    let params = request.params();
+    let velocities = match request.id() {
+        CommandID::GetSetPtpJointParams => {
+            decode_params::<PtpJointParams>(params)?.velocity.to_vec()
+        }
+        CommandID::GetSetPtpCoordinateParams => {
+            let params: PtpCoordinateParams = decode_params(params)?;
+            vec![params.xyz_velocity, params.r_velocity]
+        }
+        CommandID::GetSetPtpCommonParams => {
+            vec![decode_params::<PtpCommonParams>(params)?.velocity_ratio]
+        }
+        CommandID::GetSetPtpLParams => vec![decode_params::<PtpLParams>(params)?.velocity],
+        CommandID::GetSetArcParams => {
+            let params: ArcParams = decode_params(params)?;
+            vec![params.xyz_velocity, params.r_velocity]
+        }
+        CommandID::GetSetJogJointParams => {
+            decode_params::<JogJointParams>(params)?.velocity.to_vec()
+        }
+        CommandID::GetSetJogCoordinateParams => decode_params::<JogCoordinateParams>(params)?
+            .velocity
+            .to_vec(),
+        CommandID::GetSetJogCommonParams => {
+            vec![decode_params::<JogCommonParams>(params)?.velocity_ratio]
+        }
+        CommandID::GetSetJogLParams => vec![decode_params::<JogLParams>(params)?.velocity],
+        _ => return Ok(()),
+    };
+    match velocities
+        .iter()
+        .all(|velocity| velocity.is_finite() && *velocity > 0.0)
+    {
+        true => Ok(()),
+        false => Err(DobotError::InvalidParams(request.id())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dobot::{
+        base::Dobot,
+        command::{
+            Command, GetJogCommonParams, GetPtpCommonParams, GetQueuedCmdCurrentIndex, JogCommand,
+            Queued, SetEMotor, SetEndEffectorSuctionCup, SetHomeCmd, SetJogCmd, SetJogCommonParams,
+            SetPtpCmd, SetPtpCommonParams, SetPtpJointParams, SetQueuedCmdClear,
+            SetQueuedCmdForceStopExec, SetQueuedCmdStartExec, SetQueuedCmdStopExec,
+        },
+    };
+
+    fn distance(a: Cartesian, b: Cartesian) -> f32 {
+        let [x, y, z] = sub([a[0], a[1], a[2]], [b[0], b[1], b[2]]);
+        (x * x + y * y + z * z).sqrt()
+    }
+
+    fn assert_near(a: Cartesian, b: Cartesian) {
+        assert!(distance(a, b) < 0.01, "{:?} is not at {:?}", a, b);
+    }
+
+    #[test]
+    fn kinematics_round_trip() {
+        for joints in [
+            [0.0, 0.0, 0.0, 0.0],
+            [30.0, 20.0, 10.0, -45.0],
+            [-60.0, 45.0, -5.0, 90.0],
+        ] {
+            let cartesian = forward_kinematics(joints);
+            let back = inverse_kinematics(cartesian).unwrap();
+            for (angle, expected) in back.iter().zip(joints) {
+                assert!(
+                    (angle - expected).abs() < 0.01,
+                    "{:?} != {:?}",
+                    back,
+                    joints
+                );
+            }
+        }
+        assert!(inverse_kinematics([1000.0, 0.0, 0.0, 0.0]).is_none());
+    }
+
+    #[test]
+    fn passes_through_the_via_point() {
+        let start = [100.0, 0.0, 50.0, 0.0];
+        let via = [0.0, 100.0, 50.0, 0.0];
+        let end = [-100.0, 0.0, 50.0, 90.0];
+        let points = arc_points(start, via, end, 4).unwrap();
+
+        assert_eq!(points.len(), 4);
+        let center = [0.0, 0.0, 50.0, 0.0];
+        for point in &points {
+            assert!((distance(*point, center) - 100.0).abs() < 0.01);
+        }
+        assert_near(points[1], via);
+        assert_near(points[3], end);
+        // The rotation is interpolated from start to end.
+        assert_eq!(points[1][3], 45.0);
+        assert_eq!(points[3][3], 90.0);
+    }
+
+    #[test]
+    fn turns_towards_the_via_point() {
+        // The same end points, the arc goes the other way round.
+        let start = [100.0, 0.0, 50.0, 0.0];
+        let via = [0.0, -100.0, 50.0, 0.0];
+        let end = [-100.0, 0.0, 50.0, 0.0];
+        let points = arc_points(start, via, end, 4).unwrap();
+        assert_near(points[1], via);
+        assert!(points.iter().all(|point| point[1] <= 0.01));
+    }
+
+    #[test]
+    fn follows_arcs_longer_than_half_a_circle() {
+        let start = [100.0, 0.0, 0.0, 0.0];
+        let via = [0.0, 100.0, 0.0, 0.0];
+        let end = [0.0, -100.0, 0.0, 0.0];
+        let points = arc_points(start, via, end, 6).unwrap();
+        assert_near(points[1], via);
+        assert_near(points[3], [-100.0, 0.0, 0.0, 0.0]);
+        assert_near(points[5], end);
+    }
+
+    #[test]
+    fn follows_arcs_in_a_vertical_plane() {
+        let start = [200.0, 0.0, 0.0, 0.0];
+        let via = [250.0, 0.0, 50.0, 0.0];
+        let end = [200.0, 0.0, 100.0, 0.0];
+        let points = arc_points(start, via, end, 2).unwrap();
+        assert_near(points[0], via);
+        assert_near(points[1], end);
+    }
+
+    #[test]
+    fn rejects_points_on_a_line() {
+        let start = [100.0, 0.0, 0.0, 0.0];
+        let via = [150.0, 0.0, 0.0, 0.0];
+        let end = [200.0, 0.0, 0.0, 0.0];
+        assert!(arc_points(start, via, end, 8).is_none());
+        assert!(arc_points(start, start, end, 8).is_none());
+    }
+
+    fn point(x: f32, y: f32, z: f32) -> CartesianPoint {
+        CartesianPoint { x, y, z, r: 0.0 }
+    }
+
+    #[test]
+    fn moves_the_arm_on_an_arc() {
+        let mut dobot = Dobot::builder().connect(Emulator::new().spawn()).unwrap();
+        dobot
+            .set_arc_cmd(point(220.0, 40.0, 100.0), point(200.0, 80.0, 60.0))
+            .unwrap()
+            .wait_timeout(Duration::from_secs(5))
+            .unwrap();
+
+        let pose = dobot.get_pose().unwrap();
+        assert_near([pose.x, pose.y, pose.z, 0.0], [200.0, 80.0, 60.0, 0.0]);
+        assert!(dobot.get_alarms().unwrap().is_empty());
+    }
+
+    #[test]
+    fn raises_an_alarm_for_a_straight_arc() {
+        let mut dobot = Dobot::builder().connect(Emulator::new().spawn()).unwrap();
+        let start = dobot.get_pose().unwrap();
+        dobot
+            .set_arc_cmd(
+                point(start.x - 20.0, start.y, start.z),
+                point(start.x - 40.0, start.y, start.z),
+            )
+            .unwrap()
+            .wait_timeout(Duration::from_secs(5))
+            .unwrap();
+
+        assert!(dobot.get_alarms().unwrap().contains(Alarm::PlanArcInput));
+        let pose = dobot.get_pose().unwrap();
+        assert_near(
+            [pose.x, pose.y, pose.z, 0.0],
+            [start.x, start.y, start.z, 0.0],
+        );
+    }
+
+    fn send<C: Command>(emulator: &mut Emulator, command: C) -> DobotResult<C::Response> {
+        let response = emulator.handle(&command.to_message()?)?;
+        C::decode_response(&response)
+    }
+
+    fn current_index(emulator: &mut Emulator) -> u64 {
+        send(emulator, GetQueuedCmdCurrentIndex).unwrap()
+    }
+
+    #[test]
+    fn rejects_velocities_the_arm_cannot_move_at() {
+        let mut emulator = Emulator::new();
+        for velocity_ratio in [0.0, -50.0, f32::NAN, f32::INFINITY] {
+            let params = PtpCommonParams {
+                velocity_ratio,
+                acceleration_ratio: 50.0,
+            };
+            let result = send(&mut emulator, SetPtpCommonParams(params));
+            assert!(matches!(
+                result,
+                Err(DobotError::InvalidParams(CommandID::GetSetPtpCommonParams))
+            ));
+        }
+        let params = PtpJointParams {
+            velocity: [100.0, 100.0, f32::NAN, 100.0],
+            acceleration: [100.0; 4],
+        };
+        assert!(send(&mut emulator, SetPtpJointParams(params)).is_err());
+
+        // Rejected parameters are not stored.
+        assert!(send(&mut emulator, GetPtpCommonParams).is_err());
+    }
+
+    #[test]
+    fn executes_the_queue_once_started() {
+        let mut emulator = Emulator::new();
+        let suction_cup = EndEffectorSuctionCup {
+            is_ctrl_enabled: true,
+            is_sucked: true,
+        };
+        let index = send(&mut emulator, Queued(SetEndEffectorSuctionCup(suction_cup))).unwrap();
+        assert!(!emulator.is_suction_on());
+        assert!(current_index(&mut emulator) < index);
+
+        send(&mut emulator, SetQueuedCmdStartExec).unwrap();
+        assert!(emulator.is_suction_on());
+        assert_eq!(current_index(&mut emulator), index);
+    }
+
+    #[test]
+    fn ptp_moves_take_time() {
+        let mut emulator = Emulator::new();
+        send(&mut emulator, SetQueuedCmdStartExec).unwrap();
+        // 180 degrees at the default joint velocity of 200 degrees per second.
+        let cmd = PtpCmd {
+            mode: Mode::MODE_PTP_MOVJ_ANGLE,
+            x: 180.0,
+            y: 0.0,
+            z: 0.0,
+            r: 0.0,
+        };
+        let index = send(&mut emulator, Queued(SetPtpCmd(cmd))).unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+        let j1 = emulator.pose().j1;
+        assert!(j1 > 0.0 && j1 < 180.0, "j1 is {}", j1);
+        assert!(current_index(&mut emulator) < index);
+
+        thread::sleep(Duration::from_millis(900));
+        assert_eq!(emulator.pose().j1, 180.0);
+        assert_eq!(current_index(&mut emulator), index);
+    }
+
+    #[test]
+    fn tracks_the_home_state_and_the_stepper_motors() {
+        let mut emulator = Emulator::new();
+        assert!(!emulator.is_homed());
+        send(&mut emulator, SetQueuedCmdStartExec).unwrap();
+        send(&mut emulator, Queued(SetHomeCmd)).unwrap();
+        assert!(emulator.is_homed());
+
+        let mut e_motor = EMotor {
+            index: 1,
+            is_enabled: true,
+            speed: 5000,
+        };
+        send(&mut emulator, SetEMotor(e_motor)).unwrap();
+        assert_eq!(emulator.e_motor_speed(0), 0);
+        assert_eq!(emulator.e_motor_speed(1), 5000);
+
+        e_motor.is_enabled = false;
+        send(&mut emulator, SetEMotor(e_motor)).unwrap();
+        assert_eq!(emulator.e_motor_speed(1), 0);
+    }
+
+    fn jog_cmd(cmd: JogCommand) -> SetJogCmd {
+        SetJogCmd(JogCmd {
+            is_joint: true,
+            cmd,
+        })
+    }
+
+    #[test]
+    fn jogs_an_axis_until_stopped() {
+        let mut emulator = Emulator::new();
+        send(&mut emulator, jog_cmd(JogCommand::Axis1Negative)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        send(&mut emulator, jog_cmd(JogCommand::Idle)).unwrap();
+
+        // The default jog velocity of j1 is 15 degrees per second.
+        let stopped = emulator.pose();
+        assert!(
+            stopped.j1 < -1.0 && stopped.j1 > -10.0,
+            "j1 is {}",
+            stopped.j1
+        );
+        assert_eq!(stopped.j2, 0.0);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(emulator.pose().j1, stopped.j1);
+    }
+
+    #[test]
+    fn rejects_jog_velocities_which_are_not_finite() {
+        let mut emulator = Emulator::new();
+        for velocity_ratio in [f32::NAN, f32::INFINITY] {
+            let params = JogCommonParams {
+                velocity_ratio,
+                acceleration_ratio: 50.0,
+            };
+            let result = send(&mut emulator, SetJogCommonParams(params));
+            assert!(matches!(
+                result,
+                Err(DobotError::InvalidParams(CommandID::GetSetJogCommonParams))
+            ));
+        }
+        assert!(send(&mut emulator, GetJogCommonParams).is_err());
+
+        // Jogging still uses the default velocity.
+        send(&mut emulator, jog_cmd(JogCommand::Axis1Positive)).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(emulator.pose().j1.is_finite());
+    }
+
+    /// A queued move of the first joint to the given angle.
+    fn move_j1(j1: f32) -> Queued<SetPtpCmd> {
+        Queued(SetPtpCmd(PtpCmd {
+            mode: Mode::MODE_PTP_MOVJ_ANGLE,
+            x: j1,
+            y: 0.0,
+            z: 0.0,
+            r: 0.0,
+        }))
+    }
+
+    #[test]
+    fn pauses_the_queue_once_the_current_move_is_done() {
+        let mut emulator = Emulator::new();
+        send(&mut emulator, SetQueuedCmdStartExec).unwrap();
+        let first = send(&mut emulator, move_j1(180.0)).unwrap();
+        let second = send(&mut emulator, move_j1(0.0)).unwrap();
+        send(&mut emulator, SetQueuedCmdStopExec).unwrap();
+
+        thread::sleep(Duration::from_millis(1100));
+        assert_eq!(emulator.pose().j1, 180.0);
+        assert_eq!(current_index(&mut emulator), first);
+
+        send(&mut emulator, SetQueuedCmdStartExec).unwrap();
+        thread::sleep(Duration::from_millis(1100));
+        assert_eq!(emulator.pose().j1, 0.0);
+        assert_eq!(current_index(&mut emulator), second);
+    }
+
+    #[test]
+    fn force_stop_ends_the_current_move_where_the_arm_is() {
+        let mut emulator = Emulator::new();
+        send(&mut emulator, SetQueuedCmdStartExec).unwrap();
+        let index = send(&mut emulator, move_j1(180.0)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        send(&mut emulator, SetQueuedCmdForceStopExec).unwrap();
+
+        // Waiting for the stopped move finishes.
+        assert_eq!(current_index(&mut emulator), index);
+        let stopped = emulator.pose().j1;
+        assert!(stopped > 0.0 && stopped < 180.0, "j1 is {}", stopped);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(emulator.pose().j1, stopped);
+    }
+
+    #[test]
+    fn aborting_drops_the_queued_moves() {
+        let mut emulator = Emulator::new();
+        send(&mut emulator, SetQueuedCmdStartExec).unwrap();
+        let first = send(&mut emulator, move_j1(180.0)).unwrap();
+        send(&mut emulator, move_j1(0.0)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        send(&mut emulator, SetQueuedCmdForceStopExec).unwrap();
+        send(&mut emulator, SetQueuedCmdClear).unwrap();
+        send(&mut emulator, SetQueuedCmdStartExec).unwrap();
+
+        let stopped = emulator.pose().j1;
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(emulator.pose().j1, stopped);
+        assert_eq!(current_index(&mut emulator), first);
+
+        // Moves queued after the abort are executed.
+        let index = send(&mut emulator, move_j1(stopped + 10.0)).unwrap();
+        thread::sleep(Duration::from_millis(500));
+        assert_eq!(emulator.pose().j1, stopped + 10.0);
+        assert_eq!(current_index(&mut emulator), index);
+    }
+}
@@ -1,10 +1,11 @@
+use crate::dobot::base::CommandID;
 use failure::Fail;
 use std::io::Error as StdIoError;
 
 /// Error type for dobot crate.
 #[derive(Debug, Fail)]
 pub enum Error {
-    #[fail(display = "the size of params can be up to 254 bytes")]
+    #[fail(display = "the size of params can be up to 253 bytes")]
     ParamsTooLong,
     #[fail(display = "fail to deserialize message: {}", _0)]
     Deserialize(String),
@@ -15,6 +16,24 @@ pub enum Error {
         received, expected
     )]
     Integrity { received: u8, expected: u8 },
+    #[fail(display = "bad frame header {:02x?}", _0)]
+    BadHeader([u8; 2]),
+    #[fail(display = "timed out waiting for a response")]
+    Timeout,
+    #[fail(
+        display = "response {:?} with ctrl {:#04x} does not match request {:?} with ctrl {:#04x}",
+        received, received_ctrl, expected, expected_ctrl
+    )]
+    ResponseMismatch {
+        expected: CommandID,
+        expected_ctrl: u8,
+        received: CommandID,
+        received_ctrl: u8,
+    },
+    #[fail(display = "timed out waiting for queued command {} to finish", index)]
+    WaitTimeout { index: u64 },
+    #[fail(display = "invalid parameters for {:?}", _0)]
+    InvalidParams(CommandID),
 }
 
 impl From<StdIoError> for Error {
@@ -5,6 +5,15 @@ use crate::dobot::{
 use num_traits::FromPrimitive;
 use std::{convert::TryInto, io::prelude::*};
 
+/// Start of every frame of the Dobot protocol.
+const HEADER: [u8; 2] = [0xaa, 0xaa];
+
+/// Smallest valid value of the length byte: command ID and ctrl byte.
+const MIN_LEN: u8 = 2;
+
+/// Most parameter bytes which fit next to the command ID and ctrl byte in the length byte.
+const MAX_PARAMS_LEN: usize = (u8::MAX - MIN_LEN) as usize;
+
 /// The message format of Dobot protocol.
 #[derive(Clone, Debug)]
 pub struct DobotMessage {
@@ -20,15 +29,15 @@ pub struct DobotMessage {
 impl DobotMessage {
     /// Create message object.
     pub fn new(id: CommandID, rw: bool, is_queued: bool, params: Vec<u8>) -> DobotResult<Self> {
-        if params.len() > u8::max_value() as usize + 2 {
+        if params.len() > MAX_PARAMS_LEN {
             return Err(DobotError::ParamsTooLong);
         }
 
-        let len = params.len() as u8 + 2;
+        let len = params.len() as u8 + MIN_LEN;
         let checksum = Self::compute_checksum(id, rw, is_queued, &params);
 
         let msg = Self {
-            header: [0xaa, 0xaa],
+            header: HEADER,
             len,
             id,
             rw,
@@ -42,7 +51,7 @@ impl DobotMessage {
 
     /// Serialize message to bytes.
     pub fn to_bytes(&self) -> Vec<u8> {
-        let ctrl = ((self.is_queued as u8) << 1) | (self.rw as u8);
+        let ctrl = self.ctrl();
         self.header
             .iter()
             .chain([self.len].iter())
@@ -64,6 +73,9 @@ impl DobotMessage {
         }
 
         let header: [u8; 2] = as_ref[0..2].try_into().unwrap();
+        if header != HEADER {
+            return Err(DobotError::BadHeader(header));
+        }
         let len = as_ref[2];
 
         if as_ref.len() != len as usize + 4 {
@@ -103,55 +115,23 @@ impl DobotMessage {
         Ok(msg)
     }
 
-    pub fn _from_reader<R>(mut reader: R) -> DobotResult<Self>
-    where
-        R: Read,
-    {
-        let header_buffer = {
-            let mut header = [0; 2];
-            reader.read_exact(&mut header)?;
-            header
-        };
-        let len_buffer = {
-            let mut len = [0; 1];
-            reader.read_exact(&mut len)?;
-            len
-        };
-        let len = len_buffer[0];
-        let data_buffer = {
-            let mut data = vec![0; len as usize];
-            reader.read_exact(&mut data)?;
-            data
-        };
-        let bytes = [
-            header_buffer.as_slice(),
-            len_buffer.as_slice(),
-            data_buffer.as_slice(),
-        ]
-        .concat();
-        let msg = Self::from_bytes(bytes)?;
-        Ok(msg)
-    }
-
-    /// Create message by asynchronously reading bytes from reader.
+    /// Create message by reading bytes from reader until a valid frame is found.
+    ///
+    /// Bytes which do not belong to a valid frame are skipped. The reader is
+    /// consumed one byte at a time so that nothing after the frame is lost.
     pub fn from_async_reader<R>(mut reader: R) -> DobotResult<Self>
     where
         R: Read,
     {
-        let prefix = {
-            let mut prefix = [0u8; 5];
-            reader.read_exact(&mut prefix)?;
-            prefix
-        };
-        let len = prefix[2];
-        let suffix = {
-            let mut suffix = vec![0u8; len as usize - 1];
-            reader.read_exact(suffix.as_mut_slice())?;
-            suffix
-        };
-        let bytes = [prefix.as_slice(), suffix.as_slice()].concat();
-        let msg = Self::from_bytes(bytes)?;
-        Ok(msg)
+        let mut decoder = FrameDecoder::new();
+        let mut byte = [0u8; 1];
+        loop {
+            reader.read_exact(&mut byte)?;
+            decoder.push(&byte);
+            if let Some(msg) = decoder.next_message() {
+                break Ok(msg);
+            }
+        }
     }
 
     fn compute_checksum(id: CommandID, rw: bool, is_queued: bool, params: &[u8]) -> u8 {
@@ -168,7 +148,247 @@ impl DobotMessage {
         checksum
     }
 
+    pub fn id(&self) -> CommandID {
+        self.id
+    }
+
+    pub fn rw(&self) -> bool {
+        self.rw
+    }
+
+    pub fn is_queued(&self) -> bool {
+        self.is_queued
+    }
+
+    /// The ctrl byte holding the rw and is_queued flags.
+    pub fn ctrl(&self) -> u8 {
+        ((self.is_queued as u8) << 1) | (self.rw as u8)
+    }
+
+    /// Whether sending the message twice has the same effect as sending it once.
+    ///
+    /// This holds for getters and for parameter sets which are executed
+    /// immediately, but not for queued commands or commands starting an action.
+    pub fn is_idempotent(&self) -> bool {
+        let starts_action = self.rw && self.id.is_action();
+        !self.is_queued && !starts_action
+    }
+
     pub fn params(&self) -> &[u8] {
         &self.params
     }
 }
+
+enum Frame {
+    Valid { msg: DobotMessage, len: usize },
+    Incomplete,
+    Invalid,
+}
+
+/// Reassembles messages from a byte stream which may contain noise.
+///
+/// Bytes are pushed as they arrive. The decoder hunts for the frame header
+/// and drops every byte which is not part of a frame with a valid length,
+/// command ID and checksum.
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    discarded: usize,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Extract the next valid message from the bytes received so far.
+    pub fn next_message(&mut self) -> Option<DobotMessage> {
+        let mut keep_from = None;
+        let mut start = 0;
+        while let Some(offset) = Self::find_header(&self.buffer[start..]) {
+            let candidate = start + offset;
+            match self.frame_at(candidate) {
+                Frame::Valid { msg, len } => {
+                    self.discard(candidate);
+                    self.buffer.drain(..len);
+                    return Some(msg);
+                }
+                // A later header may still turn out to start a complete frame,
+                // in which case this one was noise.
+                Frame::Incomplete => {
+                    keep_from.get_or_insert(candidate);
+                }
+                Frame::Invalid => (),
+            }
+            start = candidate + 1;
+        }
+
+        let keep_from = keep_from.unwrap_or(match self.buffer.last() {
+            Some(&byte) if byte == HEADER[0] => self.buffer.len() - 1,
+            _ => self.buffer.len(),
+        });
+        self.discard(keep_from);
+        None
+    }
+
+    /// Number of bytes dropped because they were not part of a valid frame.
+    pub fn discarded(&self) -> usize {
+        self.discarded
+    }
+
+    /// Drop all bytes received so far.
+    pub fn clear(&mut self) {
+        self.discard(self.buffer.len());
+    }
+
+    fn discard(&mut self, count: usize) {
+        self.buffer.drain(..count);
+        self.discarded += count;
+    }
+
+    fn find_header(bytes: &[u8]) -> Option<usize> {
+        bytes
+            .windows(HEADER.len())
+            .position(|window| window == HEADER)
+    }
+
+    fn frame_at(&self, start: usize) -> Frame {
+        let bytes = &self.buffer[start..];
+        let Some(&len) = bytes.get(2) else {
+            return Frame::Incomplete;
+        };
+        if len < MIN_LEN {
+            return Frame::Invalid;
+        }
+        let frame_len = len as usize + 4;
+        if bytes.len() < frame_len {
+            return Frame::Incomplete;
+        }
+        match DobotMessage::from_bytes(&bytes[..frame_len]) {
+            Ok(msg) => Frame::Valid {
+                msg,
+                len: frame_len,
+            },
+            Err(_) => Frame::Invalid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(id: CommandID, params: &[u8]) -> Vec<u8> {
+        DobotMessage::new(id, true, false, params.to_vec())
+            .unwrap()
+            .to_bytes()
+    }
+
+    #[test]
+    fn round_trips_a_message() {
+        let bytes = frame(CommandID::SetEMotor, &[0, 1, 0x10, 0x27, 0, 0]);
+        let msg = DobotMessage::from_bytes(&bytes).unwrap();
+        assert_eq!(msg.id(), CommandID::SetEMotor);
+        assert!(msg.rw());
+        assert!(!msg.is_queued());
+        assert_eq!(msg.params(), &[0, 1, 0x10, 0x27, 0, 0]);
+        assert_eq!(msg.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn fits_the_params_into_the_length_byte() {
+        let msg =
+            DobotMessage::new(CommandID::GetSetDeviceName, true, false, vec![b'a'; 253]).unwrap();
+        let bytes = msg.to_bytes();
+        assert_eq!(bytes[2], 255);
+        assert_eq!(
+            DobotMessage::from_bytes(&bytes).unwrap().params().len(),
+            253
+        );
+
+        for len in [254, 257, 300] {
+            let result = DobotMessage::new(CommandID::GetSetDeviceName, true, false, vec![0; len]);
+            assert!(matches!(result, Err(DobotError::ParamsTooLong)));
+        }
+    }
+
+    #[test]
+    fn rejects_a_bad_checksum() {
+        let mut bytes = frame(CommandID::GetPose, &[]);
+        *bytes.last_mut().unwrap() ^= 0xff;
+        assert!(matches!(
+            DobotMessage::from_bytes(&bytes),
+            Err(DobotError::Integrity { .. })
+        ));
+    }
+
+    #[test]
+    fn skips_garbage_before_a_frame() {
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&[0x00, 0xaa, 0x13, 0xaa, 0xaa, 0x01]);
+        decoder.push(&frame(CommandID::GetPose, &[]));
+        let msg = decoder.next_message().unwrap();
+        assert_eq!(msg.id(), CommandID::GetPose);
+        assert_eq!(decoder.discarded(), 6);
+        assert!(decoder.next_message().is_none());
+    }
+
+    #[test]
+    fn resyncs_after_a_bad_checksum() {
+        let mut corrupted = frame(CommandID::GetPose, &[]);
+        *corrupted.last_mut().unwrap() ^= 0xff;
+        let valid = frame(CommandID::GetAlarmsState, &[]);
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&corrupted);
+        decoder.push(&valid);
+        let msg = decoder.next_message().unwrap();
+        assert_eq!(msg.id(), CommandID::GetAlarmsState);
+        assert_eq!(decoder.discarded(), corrupted.len());
+    }
+
+    #[test]
+    fn resyncs_after_a_truncated_frame() {
+        // The header of a frame which never completes is followed by a whole frame.
+        let truncated = &frame(CommandID::SetEMotor, &[0; 6])[..5];
+        let valid = frame(CommandID::GetPose, &[]);
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(truncated);
+        assert!(decoder.next_message().is_none());
+        decoder.push(&valid);
+        let msg = decoder.next_message().unwrap();
+        assert_eq!(msg.id(), CommandID::GetPose);
+        assert_eq!(decoder.discarded(), truncated.len());
+    }
+
+    #[test]
+    fn waits_for_the_rest_of_a_frame() {
+        let bytes = frame(CommandID::GetPose, &[1, 2, 3]);
+        let (head, tail) = bytes.split_at(4);
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(head);
+        assert!(decoder.next_message().is_none());
+        decoder.push(tail);
+        assert_eq!(decoder.next_message().unwrap().params(), &[1, 2, 3]);
+        assert_eq!(decoder.discarded(), 0);
+    }
+
+    #[test]
+    fn reads_the_first_valid_frame() {
+        let mut bytes = vec![0x55, 0xaa];
+        bytes.extend(frame(CommandID::GetPose, &[]));
+        bytes.extend(frame(CommandID::GetAlarmsState, &[]));
+        let mut reader = bytes.as_slice();
+
+        let msg = DobotMessage::from_async_reader(&mut reader).unwrap();
+        assert_eq!(msg.id(), CommandID::GetPose);
+        let msg = DobotMessage::from_async_reader(&mut reader).unwrap();
+        assert_eq!(msg.id(), CommandID::GetAlarmsState);
+    }
+}
@@ -1,6 +1,12 @@
 //! This crate provides high-level API to control Dobot robot arms.
 
+pub mod alarm;
+#[cfg(feature = "async")]
+pub mod asynchronous;
 pub mod base;
+pub mod command;
+pub mod emulator;
 pub mod error;
 pub mod message;
+pub mod transport;
 
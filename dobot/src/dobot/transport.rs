@@ -0,0 +1,131 @@
+use serial2::SerialPort;
+use std::{
+    collections::VecDeque,
+    io::{Error as IoError, ErrorKind, Read, Result as IoResult, Write},
+    net::TcpStream,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// A bidirectional byte stream over which the Dobot protocol is carried.
+pub trait Transport: Read + Write {
+    /// Limit how long a single read may block.
+    ///
+    /// A read which times out fails with `ErrorKind::TimedOut` or
+    /// `ErrorKind::WouldBlock`.
+    fn set_read_timeout(&mut self, timeout: Duration) -> IoResult<()>;
+}
+
+impl Transport for SerialPort {
+    fn set_read_timeout(&mut self, timeout: Duration) -> IoResult<()> {
+        SerialPort::set_read_timeout(self, timeout)
+    }
+}
+
+impl Transport for TcpStream {
+    fn set_read_timeout(&mut self, timeout: Duration) -> IoResult<()> {
+        // A zero timeout is rejected by the socket API.
+        TcpStream::set_read_timeout(self, Some(timeout.max(Duration::from_millis(1))))
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn set_read_timeout(&mut self, timeout: Duration) -> IoResult<()> {
+        (**self).set_read_timeout(timeout)
+    }
+}
+
+#[derive(Default)]
+struct PipeBuffer {
+    bytes: VecDeque<u8>,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct PipeChannel {
+    buffer: Mutex<PipeBuffer>,
+    readable: Condvar,
+}
+
+/// One end of an in-memory byte pipe created by [`pipe`].
+pub struct PipeEnd {
+    rx: Arc<PipeChannel>,
+    tx: Arc<PipeChannel>,
+    read_timeout: Option<Duration>,
+}
+
+/// Create a connected pair of in-memory transports.
+///
+/// Bytes written to one end can be read from the other one. Reads block
+/// until data is available, or until the read timeout of the end expires,
+/// and return end-of-file once the other end is dropped.
+pub fn pipe() -> (PipeEnd, PipeEnd) {
+    let a_to_b = Arc::new(PipeChannel::default());
+    let b_to_a = Arc::new(PipeChannel::default());
+
+    let a = PipeEnd {
+        rx: b_to_a.clone(),
+        tx: a_to_b.clone(),
+        read_timeout: None,
+    };
+    let b = PipeEnd {
+        rx: a_to_b,
+        tx: b_to_a,
+        read_timeout: None,
+    };
+    (a, b)
+}
+
+impl Read for PipeEnd {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let deadline = self.read_timeout.map(|timeout| Instant::now() + timeout);
+        let mut buffer = self.rx.buffer.lock().unwrap();
+        while buffer.bytes.is_empty() && !buffer.closed {
+            buffer = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(IoError::from(ErrorKind::TimedOut));
+                    }
+                    self.rx.readable.wait_timeout(buffer, remaining).unwrap().0
+                }
+                None => self.rx.readable.wait(buffer).unwrap(),
+            };
+        }
+
+        let len = buf.len().min(buffer.bytes.len());
+        for (dst, src) in buf.iter_mut().zip(buffer.bytes.drain(..len)) {
+            *dst = src;
+        }
+        Ok(len)
+    }
+}
+
+impl Write for PipeEnd {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        let mut buffer = self.tx.buffer.lock().unwrap();
+        buffer.bytes.extend(buf);
+        self.tx.readable.notify_all();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+impl Drop for PipeEnd {
+    fn drop(&mut self) {
+        for channel in [&self.rx, &self.tx] {
+            channel.buffer.lock().unwrap().closed = true;
+            channel.readable.notify_all();
+        }
+    }
+}
+
+impl Transport for PipeEnd {
+    fn set_read_timeout(&mut self, timeout: Duration) -> IoResult<()> {
+        self.read_timeout = Some(timeout);
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Remembers which arm was homed last and when it was powered on, so that
+//! homing can be skipped while the arm keeps its calibration.
+
+use std::{
+    fs,
+    io::{Error as IoError, ErrorKind},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Power-on times closer than this are taken to be the same power cycle.
+const POWER_ON_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// An arm and the time it was powered on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HomeRecord {
+    pub serial_number: String,
+    /// Milliseconds since the Unix epoch.
+    pub powered_on: u64,
+}
+
+impl HomeRecord {
+    /// Record of the arm with the given serial number and uptime in milliseconds.
+    pub fn new(serial_number: String, uptime_ms: u32) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            serial_number,
+            powered_on: (now.as_millis() as u64).saturating_sub(uptime_ms.into()),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, IoError> {
+        let content = fs::read_to_string(path)?;
+        content
+            .trim_end()
+            .rsplit_once(' ')
+            .and_then(|(serial_number, powered_on)| {
+                Some(Self {
+                    serial_number: serial_number.to_string(),
+                    powered_on: powered_on.parse().ok()?,
+                })
+            })
+            .ok_or_else(|| IoError::new(ErrorKind::InvalidData, "invalid home record"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), IoError> {
+        fs::write(
+            path,
+            format!("{} {}\n", self.serial_number, self.powered_on),
+        )
+    }
+
+    /// Whether both records are of the same arm, which has not been switched off in between.
+    pub fn is_same_power_cycle(&self, other: &Self) -> bool {
+        self.serial_number == other.serial_number
+            && self.powered_on.abs_diff(other.powered_on) < POWER_ON_TOLERANCE.as_millis() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn record(serial_number: &str, powered_on: u64) -> HomeRecord {
+        HomeRecord {
+            serial_number: serial_number.to_string(),
+            powered_on,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("dobot-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn tolerates_jitter_of_the_power_on_time() {
+        let homed = record("DT2218", 1_000_000);
+        assert!(homed.is_same_power_cycle(&record("DT2218", 1_000_000)));
+        assert!(homed.is_same_power_cycle(&record("DT2218", 1_001_999)));
+        assert!(homed.is_same_power_cycle(&record("DT2218", 998_001)));
+        assert!(!homed.is_same_power_cycle(&record("DT2218", 1_002_000)));
+        assert!(!homed.is_same_power_cycle(&record("DT2218", 998_000)));
+    }
+
+    #[test]
+    fn tells_arms_apart() {
+        let homed = record("DT2218", 1_000_000);
+        assert!(!homed.is_same_power_cycle(&record("DT2219", 1_000_000)));
+    }
+
+    #[test]
+    fn dates_the_power_on_from_the_uptime() {
+        let earlier = HomeRecord::new("DT2218".to_string(), 60_000);
+        let later = HomeRecord::new("DT2218".to_string(), 61_000);
+        assert!(earlier.powered_on >= later.powered_on);
+        assert!(earlier.is_same_power_cycle(&later));
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let path = temp_path("home-record");
+        let homed = record("serial with spaces", 1_700_000_000_000);
+        homed.save(&path).unwrap();
+        let loaded = HomeRecord::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), homed);
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let path = temp_path("home-record-invalid");
+        fs::write(&path, "DT2218 yesterday\n").unwrap();
+        let loaded = HomeRecord::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap_err().kind(), ErrorKind::InvalidData);
+
+        let missing = HomeRecord::load(&temp_path("home-record-missing"));
+        assert_eq!(missing.unwrap_err().kind(), ErrorKind::NotFound);
+    }
+}
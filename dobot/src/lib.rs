@@ -0,0 +1,3 @@
+//! The Dobot driver, as a library for the `dobot` node and other programs.
+
+pub mod dobot;
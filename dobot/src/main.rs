@@ -1,11 +1,25 @@
-mod dobot;
+mod config;
+mod home;
+mod motion;
+mod profile;
+mod program;
 
+use config::{Command, Config, Connection, Homing};
+use dobot::dobot;
 use dobot::{
-    base::{CommandID, Dobot},
-    message::DobotMessage,
+    alarm::{Alarm, AlarmCategory, Alarms},
+    base::Dobot,
+    command::{
+        ColorSensorReading, EndEffectorParams, EndEffectorSuctionCup, HhtTrigMode, HomeParams,
+        IoFunction, JogCommand, JogCommonParams, Mode, SensorPort, SetEndEffectorSuctionCup,
+    },
+    emulator::Emulator,
+    transport::Transport,
 };
 use dust_dds::{
-    domain::domain_participant_factory::DomainParticipantFactory,
+    domain::{
+        domain_participant::DomainParticipant, domain_participant_factory::DomainParticipantFactory,
+    },
     infrastructure::{
         listeners::NoOpListener,
         qos::{DataReaderQos, QosKind},
@@ -13,18 +27,40 @@ use dust_dds::{
         status::NO_STATUS,
         time::DurationKind,
     },
-    subscription::sample_info::{SampleStateKind, ANY_INSTANCE_STATE, ANY_VIEW_STATE},
+    publication::{data_writer::DataWriter, publisher::Publisher},
+    subscription::{
+        data_reader::DataReader,
+        sample_info::{SampleStateKind, ANY_INSTANCE_STATE, ANY_VIEW_STATE},
+        subscriber::Subscriber,
+    },
 };
+use home::HomeRecord;
+use motion::{belt_motor, cartesian_point, move_arm, ptp_mode};
+use num_traits::FromPrimitive;
+use profile::ProfileParams;
+use program::{Player, Program, Step, StepMove};
 use std::{
     io::Write,
-    time::Instant,
+    path::Path,
+    time::{Duration, Instant},
+};
+use types::{
+    pose_library::PoseLibrary, ClearAlarms, Color, DobotAlarms, DobotHoming, DobotHousekeeping,
+    DobotIoAnalog, DobotIoLevel, DobotIoPwm, DobotJogCommand, DobotLostSteps, DobotMotionProfile,
+    DobotMotionState, DobotMoveCommand, DobotPose, DobotProgramCommand, DobotProgramState,
+    DobotQueueControl, EndEffectorCommand, EndEffectorState, EndEffectorType, HomeDobot,
+    HomingState, MotorSpeed, MoveType, Presence, ProgramAction, QueueAction, SensorState, Suction,
 };
-use types::{DobotPose, MotorSpeed, Suction};
-
-const MIN_BELT_SPEED: i32 = 500;
-const MAX_BELT_SPEED: i32 = 15000;
 
 const LOOP_PERIOD: std::time::Duration = std::time::Duration::from_millis(20);
+/// How often the alarms of the arm are read and published.
+const ALARM_PERIOD: Duration = Duration::from_millis(500);
+/// How often the identity and uptime of the arm are read and published.
+const HOUSEKEEPING_PERIOD: Duration = Duration::from_secs(5);
+/// How often the extended I/O inputs are read and published.
+const IO_PERIOD: Duration = Duration::from_millis(100);
+/// How often the sensors of the sensor kit are read and published.
+const SENSOR_PERIOD: Duration = Duration::from_millis(100);
 
 fn show_dobot_pose(pose: &DobotPose) -> String {
     format!(
@@ -33,142 +69,688 @@ fn show_dobot_pose(pose: &DobotPose) -> String {
     )
 }
 
-fn speed_to_command_bytes(speed: i32) -> Vec<u8> {
-    let speed = match speed {
-        0 => 0,
-        s => s.clamp(MIN_BELT_SPEED, MAX_BELT_SPEED),
+/// Queue switching the end effector selected by the command.
+fn switch_end_effector<T: Transport>(
+    dobot: &mut Dobot<T>,
+    tool: EndEffectorType,
+    command: &EndEffectorCommand,
+) -> Result<(), dobot::error::Error> {
+    match tool {
+        EndEffectorType::SuctionCup => {
+            dobot.queue(SetEndEffectorSuctionCup(EndEffectorSuctionCup {
+                is_ctrl_enabled: command.is_enabled,
+                is_sucked: command.is_active,
+            }))?
+        }
+        EndEffectorType::Gripper => {
+            dobot.set_end_effector_gripper(command.is_enabled, command.is_active)?
+        }
+        EndEffectorType::Laser => {
+            dobot.set_end_effector_laser(command.is_enabled, command.is_active)?
+        }
     };
-    [&[0, 1], &speed.to_le_bytes() as &[u8]].concat()
+    Ok(())
+}
+
+fn show_alarms(alarms: &Alarms) -> String {
+    if alarms.is_empty() {
+        return "no alarms".to_string();
+    }
+    alarms
+        .codes()
+        .map(|code| match Alarm::from_u8(code) {
+            Some(alarm) => alarm.to_string(),
+            None => format!("{:#04x} unknown alarm", code),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Axes, from 0 to 3, of the lost-step alarms raised in `current` but not in `previous`.
+fn lost_step_axes(previous: &Alarms, current: &Alarms) -> Vec<u8> {
+    current
+        .iter()
+        .filter(|&alarm| alarm.category() == AlarmCategory::LostStep && !previous.contains(alarm))
+        .map(|alarm| alarm.code() - Alarm::LostStepAxis1.code())
+        .collect()
+}
+
+/// The jog command to send, `None` if the axis is unknown.
+fn jog_command(jog: &DobotJogCommand) -> Option<JogCommand> {
+    match jog.direction {
+        0 => Some(JogCommand::Idle),
+        direction => JogCommand::new(jog.axis, direction > 0),
+    }
+}
+
+/// The colour as published by the `color_sensor` node, with the detected channel at 255.
+fn sensor_color(reading: &ColorSensorReading) -> Color {
+    let max = reading.red.max(reading.green).max(reading.blue);
+    let saturate = |channel: u8| match max > 0 && channel == max {
+        true => 255,
+        false => 0,
+    };
+    Color {
+        red: saturate(reading.red),
+        green: saturate(reading.green),
+        blue: saturate(reading.blue),
+    }
+}
+
+/// Queue homing, returning its queue index and when it started.
+fn start_homing<T: Transport>(dobot: &mut Dobot<T>) -> Result<(u64, Instant), dobot::error::Error> {
+    let index = dobot.set_home()?.index();
+    Ok((index, Instant::now()))
+}
+
+fn home_record<T: Transport>(dobot: &mut Dobot<T>) -> Result<HomeRecord, dobot::error::Error> {
+    Ok(HomeRecord::new(
+        dobot.get_device_sn()?,
+        dobot.get_device_time()?,
+    ))
+}
+
+/// Whether the arm has not been switched off since it was last homed.
+fn is_calibrated<T: Transport>(dobot: &mut Dobot<T>, path: &Path) -> bool {
+    let Ok(record) = HomeRecord::load(path) else {
+        return false;
+    };
+    home_record(dobot).is_ok_and(|current| current.is_same_power_cycle(&record))
+}
+
+fn read_pose<T: Transport>(dobot: &mut Dobot<T>) -> Result<DobotPose, dobot::error::Error> {
+    let pose = dobot.get_pose()?;
+    Ok(DobotPose {
+        x: pose.x,
+        y: pose.y,
+        z: pose.z,
+        r: pose.r,
+        l: pose.l,
+    })
+}
+
+/// Record the current pose of the arm under the given name in the pose library.
+fn teach_pose<T: Transport>(
+    dobot: &mut Dobot<T>,
+    name: &str,
+    path: &Path,
+) -> Result<DobotPose, dobot::error::Error> {
+    let pose = read_pose(dobot)?;
+    let mut library = PoseLibrary::load_or_default(path)?;
+    library.insert(name.to_string(), pose);
+    library.save(path)?;
+    Ok(pose)
+}
+
+/// Append a step to the program file.
+fn record_step(path: &Path, step: &Step) {
+    match Program::append(path, step) {
+        Ok(()) => println!("\nRecorded {}", step),
+        Err(error) => eprintln!("\nFailed to record {}: {}", step, error),
+    }
+}
+
+/// Append a move to the current pose of the arm to the program file.
+fn record_pose<T: Transport>(dobot: &mut Dobot<T>, path: &Path) {
+    match read_pose(dobot) {
+        Ok(pose) => {
+            let move_type = StepMove::MovJ;
+            record_step(path, &Step::Move { move_type, pose });
+        }
+        Err(error) => eprintln!("\nFailed to read pose: {}", error),
+    }
+}
+
+fn read_housekeeping<T: Transport>(
+    dobot: &mut Dobot<T>,
+) -> Result<DobotHousekeeping, dobot::error::Error> {
+    Ok(DobotHousekeeping {
+        is_available: true,
+        serial_number: dobot.get_device_sn()?,
+        name: dobot.get_device_name()?,
+        firmware_version: dobot.get_device_version()?.to_string(),
+        uptime_ms: dobot.get_device_time()?,
+    })
 }
 
 fn main() -> Result<(), dobot::error::Error> {
-    let domain_id = 0;
+    let command = config::parse_args(std::env::args().skip(1)).unwrap_or_else(|message| {
+        eprintln!("{}\n\n{}", message, config::USAGE);
+        std::process::exit(2);
+    });
 
-    let mut dobot = Dobot::open().unwrap();
-    let mut suction_state = Suction { is_on: false };
+    let (config, program) = match command {
+        Command::Run(config) => (config, None),
+        Command::Play { program, config } => (config, Some(program)),
+        Command::Emulator { listen } => {
+            println!("Emulated Dobot listening on {}", listen);
+            return Emulator::new().listen(listen);
+        }
+    };
 
-    let reliable_reader_qos = DataReaderQos {
+    match &config.connection {
+        Connection::Serial { port, baud_rate } => {
+            let dobot = Dobot::builder()
+                .port(port.as_str())
+                .baud_rate(*baud_rate)
+                .open()?;
+            start(dobot, &config, program.as_deref())
+        }
+        Connection::Tcp(addr) => {
+            let dobot = Dobot::builder().connect_tcp(addr.as_str())?;
+            start(dobot, &config, program.as_deref())
+        }
+        Connection::Emulator => {
+            let dobot = Dobot::builder().connect(Emulator::new().spawn())?;
+            start(dobot, &config, program.as_deref())
+        }
+    }
+}
+
+/// Play the program if one is given, run the node otherwise.
+fn start<T: Transport>(
+    dobot: Dobot<T>,
+    config: &Config,
+    program: Option<&Path>,
+) -> Result<(), dobot::error::Error> {
+    match program {
+        Some(path) => play(dobot, config, path),
+        None => run(dobot, config),
+    }
+}
+
+/// Play a program file, returning once the arm is done with it.
+fn play<T: Transport>(
+    mut dobot: Dobot<T>,
+    config: &Config,
+    path: &Path,
+) -> Result<(), dobot::error::Error> {
+    let mut player = Player::new(Program::load(path)?);
+    // The moves of the program use the tool offset and profile of the node.
+    set_up(&mut dobot, config)?;
+    println!(
+        "Playing {} steps of {}",
+        player.step_count(),
+        path.display()
+    );
+    while !player.poll(&mut dobot)? {
+        print!("STEP: {}/{}\r", player.steps_done(), player.step_count());
+        std::io::stdout().flush()?;
+        std::thread::sleep(LOOP_PERIOD);
+    }
+    println!("STEP: {}/{}", player.steps_done(), player.step_count());
+    Ok(())
+}
+
+/// Creates a topic of the node and a reliable reader of it.
+macro_rules! create_reader {
+    ($dds:expr, $topic_name:expr, $type_name:expr, $type:ty) => {{
+        let topic = $dds
+            .participant
+            .create_topic::<$type>(
+                $topic_name,
+                $type_name,
+                QosKind::Default,
+                NoOpListener::new(),
+                NO_STATUS,
+            )
+            .unwrap();
+        $dds.subscriber
+            .create_datareader::<$type>(
+                &topic,
+                QosKind::Specific(reliable_reader_qos()),
+                NoOpListener::new(),
+                NO_STATUS,
+            )
+            .unwrap()
+    }};
+}
+
+/// Creates a topic of the node and a writer of it.
+macro_rules! create_writer {
+    ($dds:expr, $topic_name:expr, $type_name:expr, $type:ty) => {{
+        let topic = $dds
+            .participant
+            .create_topic::<$type>(
+                $topic_name,
+                $type_name,
+                QosKind::Default,
+                NoOpListener::new(),
+                NO_STATUS,
+            )
+            .unwrap();
+        $dds.publisher
+            .create_datawriter(&topic, QosKind::Default, NoOpListener::new(), NO_STATUS)
+            .unwrap()
+    }};
+}
+
+fn reliable_reader_qos() -> DataReaderQos {
+    DataReaderQos {
         reliability: ReliabilityQosPolicy {
             kind: ReliabilityQosPolicyKind::Reliable,
             max_blocking_time: DurationKind::Infinite,
         },
         ..Default::default()
-    };
+    }
+}
 
-    let participant_factory = DomainParticipantFactory::get_instance();
-    let participant = participant_factory
-        .create_participant(domain_id, QosKind::Default, NoOpListener::new(), NO_STATUS)
-        .unwrap();
+/// The DDS entities the topics of the node are created with.
+struct Dds {
+    participant: DomainParticipant,
+    subscriber: Subscriber,
+    publisher: Publisher,
+}
 
-    let subscriber = participant
-        .create_subscriber(QosKind::Default, NoOpListener::new(), NO_STATUS)
-        .unwrap();
-    let publisher = participant
-        .create_publisher(QosKind::Default, NoOpListener::new(), NO_STATUS)
-        .unwrap();
+impl Dds {
+    fn new(domain_id: i32) -> Self {
+        let participant_factory = DomainParticipantFactory::get_instance();
+        let participant = participant_factory
+            .create_participant(domain_id, QosKind::Default, NoOpListener::new(), NO_STATUS)
+            .unwrap();
+        let subscriber = participant
+            .create_subscriber(QosKind::Default, NoOpListener::new(), NO_STATUS)
+            .unwrap();
+        let publisher = participant
+            .create_publisher(QosKind::Default, NoOpListener::new(), NO_STATUS)
+            .unwrap();
+        Self {
+            participant,
+            subscriber,
+            publisher,
+        }
+    }
+}
 
-    let topic_conveyor_belt_speed = participant
-        .create_topic::<MotorSpeed>(
-            "ConveyorBeltSpeed",
-            "MotorSpeed",
-            QosKind::Default,
-            NoOpListener::new(),
-            NO_STATUS,
-        )
-        .unwrap();
-    let belt_speed_reader = subscriber
-        .create_datareader::<MotorSpeed>(
-            &topic_conveyor_belt_speed,
-            QosKind::Specific(reliable_reader_qos.clone()),
-            NoOpListener::new(),
-            NO_STATUS,
-        )
-        .unwrap();
+/// Moves of the arm and the conveyor belt, and the motion of the arm.
+struct MotionTopics {
+    belt_speed_reader: DataReader<MotorSpeed>,
+    motion_profile_reader: DataReader<DobotMotionProfile>,
+    arm_movement_reader: DataReader<DobotPose>,
+    arm_move_command_reader: DataReader<DobotMoveCommand>,
+    jog_reader: DataReader<DobotJogCommand>,
+    queue_control_reader: DataReader<DobotQueueControl>,
+    pose_writer: DataWriter<DobotPose>,
+    motion_state_writer: DataWriter<DobotMotionState>,
+}
 
-    let topic_arm_movement = participant
-        .create_topic::<DobotPose>(
-            "DobotArmMovement",
-            "DobotPose",
-            QosKind::Default,
-            NoOpListener::new(),
-            NO_STATUS,
-        )
-        .unwrap();
-    let arm_movement_reader = subscriber
-        .create_datareader::<DobotPose>(
-            &topic_arm_movement,
-            QosKind::Specific(reliable_reader_qos.clone()),
-            NoOpListener::new(),
-            NO_STATUS,
-        )
-        .unwrap();
+impl MotionTopics {
+    fn new(dds: &Dds) -> Self {
+        Self {
+            belt_speed_reader: create_reader!(dds, "ConveyorBeltSpeed", "MotorSpeed", MotorSpeed),
+            motion_profile_reader: create_reader!(
+                dds,
+                "DobotMotionProfile",
+                "DobotMotionProfile",
+                DobotMotionProfile
+            ),
+            arm_movement_reader: create_reader!(dds, "DobotArmMovement", "DobotPose", DobotPose),
+            arm_move_command_reader: create_reader!(
+                dds,
+                "DobotArmMoveCommand",
+                "DobotMoveCommand",
+                DobotMoveCommand
+            ),
+            jog_reader: create_reader!(dds, "DobotJog", "DobotJogCommand", DobotJogCommand),
+            queue_control_reader: create_reader!(
+                dds,
+                "DobotQueueControl",
+                "DobotQueueControl",
+                DobotQueueControl
+            ),
+            pose_writer: create_writer!(dds, "CurrentDobotPose", "DobotPose", DobotPose),
+            motion_state_writer: create_writer!(
+                dds,
+                "DobotMotionState",
+                "DobotMotionState",
+                DobotMotionState
+            ),
+        }
+    }
+}
 
-    let topic_suction = participant
-        .create_topic::<Suction>(
-            "SuctionCup",
-            "Suction",
-            QosKind::Default,
-            NoOpListener::new(),
-            NO_STATUS,
-        )
-        .unwrap();
-    let suction_reader = subscriber
-        .create_datareader::<Suction>(
-            &topic_suction,
-            QosKind::Specific(reliable_reader_qos.clone()),
-            NoOpListener::new(),
-            NO_STATUS,
-        )
-        .unwrap();
+/// Switching the end effectors and their state.
+struct EndEffectorTopics {
+    suction_reader: DataReader<Suction>,
+    end_effector_reader: DataReader<EndEffectorCommand>,
+    suction_writer: DataWriter<Suction>,
+    end_effector_writer: DataWriter<EndEffectorState>,
+}
 
-    let topic_robot_pose = participant
-        .create_topic::<DobotPose>(
-            "CurrentDobotPose",
-            "DobotPose",
-            QosKind::Default,
-            NoOpListener::new(),
-            NO_STATUS,
-        )
-        .unwrap();
-    let pose_writer = publisher
-        .create_datawriter(
-            &topic_robot_pose,
-            QosKind::Default,
-            NoOpListener::new(),
-            NO_STATUS,
-        )
-        .unwrap();
+impl EndEffectorTopics {
+    fn new(dds: &Dds) -> Self {
+        Self {
+            suction_reader: create_reader!(dds, "SuctionCup", "Suction", Suction),
+            end_effector_reader: create_reader!(
+                dds,
+                "EndEffector",
+                "EndEffectorCommand",
+                EndEffectorCommand
+            ),
+            suction_writer: create_writer!(dds, "CurrentSuctionCupState", "Suction", Suction),
+            end_effector_writer: create_writer!(
+                dds,
+                "CurrentEndEffectorState",
+                "EndEffectorState",
+                EndEffectorState
+            ),
+        }
+    }
+}
 
-    let topic_current_suction = participant
-        .create_topic::<Suction>(
-            "CurrentSuctionCupState",
-            "Suction",
-            QosKind::Default,
-            NoOpListener::new(),
-            NO_STATUS,
-        )
-        .unwrap();
-    let suction_writer = publisher
-        .create_datawriter(
-            &topic_current_suction,
-            QosKind::Default,
-            NoOpListener::new(),
-            NO_STATUS,
-        )
-        .unwrap();
+/// Outputs and inputs of the extended I/O interface.
+struct IoTopics {
+    digital_output_reader: DataReader<DobotIoLevel>,
+    pwm_output_reader: DataReader<DobotIoPwm>,
+    digital_input_writer: DataWriter<DobotIoLevel>,
+    analog_input_writer: DataWriter<DobotIoAnalog>,
+}
+
+impl IoTopics {
+    fn new(dds: &Dds) -> Self {
+        Self {
+            digital_output_reader: create_reader!(
+                dds,
+                "DobotDigitalOutput",
+                "DobotIoLevel",
+                DobotIoLevel
+            ),
+            pwm_output_reader: create_reader!(dds, "DobotPwmOutput", "DobotIoPwm", DobotIoPwm),
+            digital_input_writer: create_writer!(
+                dds,
+                "DobotDigitalInput",
+                "DobotIoLevel",
+                DobotIoLevel
+            ),
+            analog_input_writer: create_writer!(
+                dds,
+                "DobotAnalogInput",
+                "DobotIoAnalog",
+                DobotIoAnalog
+            ),
+        }
+    }
+}
 
+/// Readings of the sensor kit.
+///
+/// Writers are only created for the sensors of the sensor kit which are
+/// plugged in, the others may be published by their own nodes.
+struct SensorTopics {
+    color_sensor_writers: Option<(DataWriter<SensorState>, DataWriter<Color>)>,
+    ir_switch_writers: Option<(SensorPort, DataWriter<SensorState>, DataWriter<Presence>)>,
+}
 
-    let params = speed_to_command_bytes(0);
-    let command = DobotMessage::new(CommandID::SetEMotor, false, false, params).unwrap();
-    dobot.send_command(command).unwrap();
-    dobot.set_end_effector_suction_cup(false).unwrap();
-    dobot.set_home().unwrap().wait().unwrap();
+impl SensorTopics {
+    fn new(dds: &Dds, config: &Config) -> Self {
+        let color_sensor_writers = config.color_sensor.map(|_| {
+            let availability_writer =
+                create_writer!(dds, "ColorSensorAvailability", "SensorState", SensorState);
+            let color_writer = create_writer!(dds, "ColorSensor", "Color", Color);
+            (availability_writer, color_writer)
+        });
+        let ir_switch_writers = config.ir_switch.map(|port| {
+            let availability_writer = create_writer!(
+                dds,
+                "PresenceSensorAvailability",
+                "SensorState",
+                SensorState
+            );
+            let presence_writer = create_writer!(dds, "Presence", "PresenceSensor", Presence);
+            (port, availability_writer, presence_writer)
+        });
+        Self {
+            color_sensor_writers,
+            ir_switch_writers,
+        }
+    }
+}
 
+/// Homing requests and progress.
+struct HomingTopics {
+    home_reader: DataReader<HomeDobot>,
+    homing_writer: DataWriter<DobotHoming>,
+}
 
-    loop {
-        let start = Instant::now();
+impl HomingTopics {
+    fn new(dds: &Dds) -> Self {
+        Self {
+            home_reader: create_reader!(dds, "HomeDobot", "HomeDobot", HomeDobot),
+            homing_writer: create_writer!(dds, "DobotHoming", "DobotHoming", DobotHoming),
+        }
+    }
+}
+
+/// Playing and recording the program, and its progress.
+struct ProgramTopics {
+    program_command_reader: DataReader<DobotProgramCommand>,
+    program_state_writer: DataWriter<DobotProgramState>,
+}
+
+impl ProgramTopics {
+    fn new(dds: &Dds) -> Self {
+        Self {
+            program_command_reader: create_reader!(
+                dds,
+                "DobotProgramCommand",
+                "DobotProgramCommand",
+                DobotProgramCommand
+            ),
+            program_state_writer: create_writer!(
+                dds,
+                "DobotProgramState",
+                "DobotProgramState",
+                DobotProgramState
+            ),
+        }
+    }
+}
+
+/// Alarms and lost steps of the arm, and clearing the alarms.
+struct AlarmTopics {
+    clear_alarms_reader: DataReader<ClearAlarms>,
+    alarms_writer: DataWriter<DobotAlarms>,
+    lost_steps_writer: DataWriter<DobotLostSteps>,
+}
+
+impl AlarmTopics {
+    fn new(dds: &Dds) -> Self {
+        Self {
+            clear_alarms_reader: create_reader!(
+                dds,
+                "ClearDobotAlarms",
+                "ClearAlarms",
+                ClearAlarms
+            ),
+            alarms_writer: create_writer!(dds, "DobotAlarms", "DobotAlarms", DobotAlarms),
+            lost_steps_writer: create_writer!(
+                dds,
+                "DobotLostSteps",
+                "DobotLostSteps",
+                DobotLostSteps
+            ),
+        }
+    }
+}
+
+/// All the topics the node reads and publishes.
+struct Topics {
+    motion: MotionTopics,
+    end_effector: EndEffectorTopics,
+    io: IoTopics,
+    sensors: SensorTopics,
+    homing: HomingTopics,
+    program: ProgramTopics,
+    alarms: AlarmTopics,
+    housekeeping_writer: DataWriter<DobotHousekeeping>,
+}
+
+impl Topics {
+    fn new(dds: &Dds, config: &Config) -> Self {
+        Self {
+            motion: MotionTopics::new(dds),
+            end_effector: EndEffectorTopics::new(dds),
+            io: IoTopics::new(dds),
+            sensors: SensorTopics::new(dds, config),
+            homing: HomingTopics::new(dds),
+            program: ProgramTopics::new(dds),
+            alarms: AlarmTopics::new(dds),
+            housekeeping_writer: create_writer!(
+                dds,
+                "DobotHousekeeping",
+                "DobotHousekeeping",
+                DobotHousekeeping
+            ),
+        }
+    }
+}
+
+/// Configure the arm and the sensor kit as given on the command line.
+fn set_up<T: Transport>(dobot: &mut Dobot<T>, config: &Config) -> Result<(), dobot::error::Error> {
+    dobot.set_e_motor(belt_motor(0))?;
+    dobot.set_end_effector_suction_cup(false)?;
+    if let Some([x_bias, y_bias, z_bias]) = config.tool_offset {
+        let params = EndEffectorParams {
+            x_bias,
+            y_bias,
+            z_bias,
+        };
+        dobot.set_end_effector_params(params)?.wait()?;
+    }
+    for &(address, function) in &config.io_pins {
+        dobot.set_io_multiplexing(address, function)?;
+    }
+    if let Some(port) = config.color_sensor {
+        dobot.set_color_sensor(true, port, config.sensor_version)?;
+    }
+    if let Some(port) = config.ir_switch {
+        dobot.set_ir_switch(true, port, config.sensor_version)?;
+    }
+    if let Some(threshold) = config.lost_step_threshold {
+        dobot.set_lost_step_params(threshold)?;
+        dobot.set_lost_step_check(true);
+    }
+    ProfileParams::of(config.motion_profile).queue(dobot)?;
+    if let Some([x, y, z, r]) = config.home_position {
+        dobot.set_home_params(HomeParams { x, y, z, r })?.wait()?;
+    }
+    if !config.teach.is_empty() || config.is_recording {
+        dobot.set_hht_trig_mode(HhtTrigMode::KeyReleased)?;
+        dobot.set_hht_trig_output_enabled(true)?;
+    }
+    if let Some(name) = config.teach.first() {
+        println!(
+            "Hold the unlock button, move the arm to {} and release the button",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// The arm driven by the node and what the node keeps track of between loops.
+struct Node<'a, T: Transport> {
+    dobot: Dobot<T>,
+    config: &'a Config,
+    suction_state: Suction,
+    end_effector_state: EndEffectorState,
+    alarms: Alarms,
+    /// Queue index of the last move, until the arm is done with it.
+    last_move_index: Option<u64>,
+    jog_speed_ratio: Option<f32>,
+    last_alarm_check: Option<Instant>,
+    last_io_check: Option<Instant>,
+    last_sensor_check: Option<Instant>,
+    last_housekeeping: Option<Instant>,
+    homing_state: HomingState,
+    /// Queue index of the homing command and when it was queued.
+    homing: Option<(u64, Instant)>,
+    /// Number of the poses given to teach which are taught already.
+    taught: usize,
+    player: Option<Player>,
+    housekeeping: DobotHousekeeping,
+}
+
+impl<'a, T: Transport> Node<'a, T> {
+    fn new(mut dobot: Dobot<T>, config: &'a Config) -> Result<Self, dobot::error::Error> {
+        set_up(&mut dobot, config)?;
+        let mut node = Self {
+            dobot,
+            config,
+            suction_state: Suction { is_on: false },
+            end_effector_state: EndEffectorState {
+                tool: EndEffectorType::SuctionCup as u8,
+                is_enabled: true,
+                is_active: false,
+            },
+            alarms: Alarms::default(),
+            last_move_index: None,
+            jog_speed_ratio: None,
+            last_alarm_check: None,
+            last_io_check: None,
+            last_sensor_check: None,
+            last_housekeeping: None,
+            homing_state: HomingState::Unknown,
+            homing: None,
+            taught: 0,
+            player: None,
+            housekeeping: DobotHousekeeping {
+                is_available: false,
+                serial_number: String::new(),
+                name: String::new(),
+                firmware_version: String::new(),
+                uptime_ms: 0,
+            },
+        };
+        node.home_on_start()?;
+        Ok(node)
+    }
+
+    /// Read the commands of all the topics, then publish the state of the arm.
+    fn poll(&mut self, topics: &Topics) {
+        self.set_belt_speed(&topics.motion.belt_speed_reader);
+        // Profiles are switched before moves requested at the same time are queued.
+        self.switch_motion_profile(&topics.motion.motion_profile_reader);
+        self.move_to_pose(&topics.motion.arm_movement_reader);
+        self.move_arm(&topics.motion.arm_move_command_reader);
+        self.jog(&topics.motion.jog_reader);
+        self.set_suction(&topics.end_effector.suction_reader);
+        self.switch_end_effector(&topics.end_effector.end_effector_reader);
+        self.set_io_outputs(&topics.io);
+        self.publish_io_inputs(&topics.io);
+        self.teach();
+        self.publish_sensors(&topics.sensors);
+        // Aborting first keeps an abort from cutting short homing requested with it.
+        self.control_queue(&topics.motion.queue_control_reader);
+        self.home(&topics.homing.home_reader);
+        self.control_program(&topics.program);
+        self.publish_homing(&topics.homing.homing_writer);
+        self.clear_alarms(&topics.alarms.clear_alarms_reader);
+        self.publish_alarms(&topics.alarms);
+        self.publish_housekeeping(&topics.housekeeping_writer);
+        self.publish_pose(&topics.motion.pose_writer);
+        self.publish_end_effector(&topics.end_effector);
+        self.publish_motion_state(&topics.motion.motion_state_writer);
+    }
 
-        if let Ok(sample_data) = belt_speed_reader.read(
+    fn home_on_start(&mut self) -> Result<(), dobot::error::Error> {
+        let is_homing_needed = match self.config.homing {
+            Homing::Always => true,
+            Homing::Never => false,
+            Homing::Auto => !is_calibrated(&mut self.dobot, &self.config.home_record),
+        };
+        if is_homing_needed {
+            self.homing = Some(start_homing(&mut self.dobot)?);
+            self.last_move_index = self.homing.map(|(index, _)| index);
+            self.homing_state = HomingState::Homing;
+        } else if self.config.homing == Homing::Auto {
+            println!("The arm is still calibrated, homing is skipped");
+            self.homing_state = HomingState::Homed;
+        }
+        Ok(())
+    }
+
+    fn set_belt_speed(&mut self, reader: &DataReader<MotorSpeed>) {
+        if let Ok(sample_data) = reader.read(
             1,
             &[SampleStateKind::NotRead],
             ANY_VIEW_STATE,
@@ -176,15 +758,42 @@ fn main() -> Result<(), dobot::error::Error> {
         ) {
             for sample in sample_data {
                 if let Ok(motor_speed) = sample.data() {
-                    let params = speed_to_command_bytes(motor_speed.speed);
-                    let command =
-                        DobotMessage::new(CommandID::SetEMotor, false, false, params).unwrap();
-                    dobot.send_command(command).unwrap();
+                    match self.dobot.set_e_motor(belt_motor(motor_speed.speed)) {
+                        Ok(()) if self.config.is_recording => {
+                            record_step(&self.config.program, &Step::Belt(motor_speed.speed))
+                        }
+                        Ok(()) => (),
+                        Err(error) => eprintln!("\nFailed to set belt speed: {}", error),
+                    }
+                }
+            }
+        }
+    }
+
+    fn switch_motion_profile(&mut self, reader: &DataReader<DobotMotionProfile>) {
+        if let Ok(sample_data) = reader.read(
+            1,
+            &[SampleStateKind::NotRead],
+            ANY_VIEW_STATE,
+            ANY_INSTANCE_STATE,
+        ) {
+            for sample in sample_data {
+                if let Ok(command) = sample.data() {
+                    match command.profile() {
+                        Ok(profile) => {
+                            if let Err(error) = ProfileParams::of(profile).queue(&mut self.dobot) {
+                                eprintln!("\nFailed to switch motion profile: {}", error);
+                            }
+                        }
+                        Err(profile) => eprintln!("\nUnknown motion profile {}", profile),
+                    }
                 }
             }
         }
+    }
 
-        if let Ok(sample_data) = arm_movement_reader.read(
+    fn move_to_pose(&mut self, reader: &DataReader<DobotPose>) {
+        if let Ok(sample_data) = reader.read(
             1,
             &[SampleStateKind::NotRead],
             ANY_VIEW_STATE,
@@ -192,20 +801,91 @@ fn main() -> Result<(), dobot::error::Error> {
         ) {
             for sample in sample_data {
                 if let Ok(pose) = sample.data() {
-                    dobot
-                        .set_ptp_cmd(
-                            pose.x,
-                            pose.y,
-                            pose.z,
-                            pose.r,
-                            dobot::base::Mode::MODE_PTP_MOVJ_XYZ,
-                        )
-                        .unwrap();
+                    match move_arm(&mut self.dobot, Mode::MODE_PTP_MOVJ_XYZ, &pose) {
+                        Ok(index) => self.last_move_index = Some(index),
+                        Err(error) => eprintln!("\nFailed to move arm: {}", error),
+                    }
+                }
+            }
+        }
+    }
+
+    fn move_arm(&mut self, reader: &DataReader<DobotMoveCommand>) {
+        if let Ok(sample_data) = reader.read(
+            1,
+            &[SampleStateKind::NotRead],
+            ANY_VIEW_STATE,
+            ANY_INSTANCE_STATE,
+        ) {
+            for sample in sample_data {
+                if let Ok(command) = sample.data() {
+                    let result = match command.move_type() {
+                        Ok(MoveType::Arc) => self
+                            .dobot
+                            .set_arc_cmd(
+                                cartesian_point(&command.via()),
+                                cartesian_point(&command.pose()),
+                            )
+                            .map(|handle| handle.index()),
+                        Ok(move_type) => {
+                            let Some(mode) = ptp_mode(move_type) else {
+                                eprintln!("\nUnsupported move type {:?}", move_type);
+                                continue;
+                            };
+                            move_arm(&mut self.dobot, mode, &command.pose())
+                        }
+                        Err(move_type) => {
+                            eprintln!("\nUnknown move type {}", move_type);
+                            continue;
+                        }
+                    };
+                    match result {
+                        Ok(index) => self.last_move_index = Some(index),
+                        Err(error) => eprintln!("\nFailed to move arm: {}", error),
+                    }
                 }
             }
         }
+    }
 
-        if let Ok(sample_data) = suction_reader.read(
+    fn jog(&mut self, reader: &DataReader<DobotJogCommand>) {
+        if let Ok(sample_data) = reader.read(
+            1,
+            &[SampleStateKind::NotRead],
+            ANY_VIEW_STATE,
+            ANY_INSTANCE_STATE,
+        ) {
+            for sample in sample_data {
+                if let Ok(jog) = sample.data() {
+                    let Some(cmd) = jog_command(&jog) else {
+                        eprintln!("\nUnknown jog axis {}", jog.axis);
+                        continue;
+                    };
+                    if !jog.speed_ratio.is_finite() {
+                        eprintln!("\nInvalid jog speed ratio {}", jog.speed_ratio);
+                        continue;
+                    }
+                    let speed_ratio = jog.speed_ratio.clamp(1.0, 100.0);
+                    if cmd != JogCommand::Idle && self.jog_speed_ratio != Some(speed_ratio) {
+                        let params = JogCommonParams {
+                            velocity_ratio: speed_ratio,
+                            acceleration_ratio: speed_ratio,
+                        };
+                        match self.dobot.set_jog_common_params(params) {
+                            Ok(()) => self.jog_speed_ratio = Some(speed_ratio),
+                            Err(error) => eprintln!("\nFailed to set jog speed: {}", error),
+                        }
+                    }
+                    if let Err(error) = self.dobot.jog(jog.is_joint, cmd) {
+                        eprintln!("\nFailed to jog arm: {}", error);
+                    }
+                }
+            }
+        }
+    }
+
+    fn set_suction(&mut self, reader: &DataReader<Suction>) {
+        if let Ok(sample_data) = reader.read(
             1,
             &[SampleStateKind::NotRead],
             ANY_VIEW_STATE,
@@ -213,24 +893,498 @@ fn main() -> Result<(), dobot::error::Error> {
         ) {
             for sample in sample_data {
                 if let Ok(suction) = sample.data() {
-                    dobot.set_end_effector_suction_cup(suction.is_on).unwrap();
-                    suction_state = suction;
+                    match self.dobot.set_end_effector_suction_cup(suction.is_on) {
+                        Ok(_) => {
+                            self.suction_state = suction;
+                            self.end_effector_state = EndEffectorState {
+                                tool: EndEffectorType::SuctionCup as u8,
+                                is_enabled: true,
+                                is_active: suction.is_on,
+                            };
+                            if self.config.is_recording {
+                                record_step(&self.config.program, &Step::Suction(suction.is_on));
+                            }
+                        }
+                        Err(error) => eprintln!("\nFailed to set suction cup: {}", error),
+                    }
+                }
+            }
+        }
+    }
+
+    fn switch_end_effector(&mut self, reader: &DataReader<EndEffectorCommand>) {
+        if let Ok(sample_data) = reader.read(
+            1,
+            &[SampleStateKind::NotRead],
+            ANY_VIEW_STATE,
+            ANY_INSTANCE_STATE,
+        ) {
+            for sample in sample_data {
+                if let Ok(command) = sample.data() {
+                    let tool = match command.tool() {
+                        Ok(tool) => tool,
+                        Err(tool) => {
+                            eprintln!("\nUnknown end effector {}", tool);
+                            continue;
+                        }
+                    };
+                    match switch_end_effector(&mut self.dobot, tool, &command) {
+                        Ok(()) => {
+                            if tool == EndEffectorType::SuctionCup {
+                                self.suction_state = Suction {
+                                    is_on: command.is_enabled && command.is_active,
+                                };
+                            }
+                            self.end_effector_state = EndEffectorState {
+                                tool: command.tool,
+                                is_enabled: command.is_enabled,
+                                is_active: command.is_active,
+                            };
+                        }
+                        Err(error) => eprintln!("\nFailed to switch end effector: {}", error),
+                    }
+                }
+            }
+        }
+    }
+
+    fn set_io_outputs(&mut self, topics: &IoTopics) {
+        if let Ok(sample_data) = topics.digital_output_reader.read(
+            1,
+            &[SampleStateKind::NotRead],
+            ANY_VIEW_STATE,
+            ANY_INSTANCE_STATE,
+        ) {
+            for sample in sample_data {
+                if let Ok(output) = sample.data() {
+                    if !self
+                        .config
+                        .io_pins
+                        .contains(&(output.address, IoFunction::DigitalOutput))
+                    {
+                        eprintln!("\nI/O pin {} is not a digital output", output.address);
+                        continue;
+                    }
+                    if let Err(error) = self.dobot.set_io_do(output.address, output.level) {
+                        eprintln!("\nFailed to set digital output: {}", error);
+                    }
+                }
+            }
+        }
+
+        if let Ok(sample_data) = topics.pwm_output_reader.read(
+            1,
+            &[SampleStateKind::NotRead],
+            ANY_VIEW_STATE,
+            ANY_INSTANCE_STATE,
+        ) {
+            for sample in sample_data {
+                if let Ok(pwm) = sample.data() {
+                    if !self
+                        .config
+                        .io_pins
+                        .contains(&(pwm.address, IoFunction::Pwm))
+                    {
+                        eprintln!("\nI/O pin {} is not a PWM output", pwm.address);
+                        continue;
+                    }
+                    if let Err(error) =
+                        self.dobot
+                            .set_io_pwm(pwm.address, pwm.frequency, pwm.duty_cycle)
+                    {
+                        eprintln!("\nFailed to set PWM output: {}", error);
+                    }
+                }
+            }
+        }
+    }
+
+    fn publish_io_inputs(&mut self, topics: &IoTopics) {
+        if self
+            .last_io_check
+            .is_none_or(|time| time.elapsed() >= IO_PERIOD)
+        {
+            self.last_io_check = Some(Instant::now());
+            for &(address, function) in &self.config.io_pins {
+                match function {
+                    IoFunction::DigitalInput => match self.dobot.get_io_di(address) {
+                        Ok(level) => topics
+                            .digital_input_writer
+                            .write(&DobotIoLevel { address, level }, None)
+                            .unwrap(),
+                        Err(error) => eprintln!("\nFailed to read digital input: {}", error),
+                    },
+                    IoFunction::Adc => match self.dobot.get_io_adc(address) {
+                        Ok(value) => topics
+                            .analog_input_writer
+                            .write(&DobotIoAnalog { address, value }, None)
+                            .unwrap(),
+                        Err(error) => eprintln!("\nFailed to read analog input: {}", error),
+                    },
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    /// Poses are taught first, then recorded into the program.
+    fn teach(&mut self) {
+        let config = self.config;
+        if self.taught < config.teach.len() || config.is_recording {
+            match self.dobot.get_hht_trig_output() {
+                Ok(true) => match config.teach.get(self.taught) {
+                    Some(name) => match teach_pose(&mut self.dobot, name, &config.pose_library) {
+                        Ok(pose) => {
+                            println!("\nTaught {} at {}", name, show_dobot_pose(&pose));
+                            self.taught += 1;
+                            match config.teach.get(self.taught) {
+                                Some(name) => println!("Move the arm to {} next", name),
+                                None => {
+                                    println!("Poses saved in {}", config.pose_library.display());
+                                    if !config.is_recording {
+                                        if let Err(error) =
+                                            self.dobot.set_hht_trig_output_enabled(false)
+                                        {
+                                            eprintln!("\nFailed to stop teaching: {}", error);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        Err(error) => eprintln!("\nFailed to teach {}: {}", name, error),
+                    },
+                    None => record_pose(&mut self.dobot, &config.program),
+                },
+                Ok(false) => (),
+                Err(error) => eprintln!("\nFailed to read teaching trigger: {}", error),
+            }
+        }
+    }
+
+    fn publish_sensors(&mut self, topics: &SensorTopics) {
+        if self
+            .last_sensor_check
+            .is_none_or(|time| time.elapsed() >= SENSOR_PERIOD)
+        {
+            self.last_sensor_check = Some(Instant::now());
+            if let Some((availability_writer, color_writer)) = &topics.color_sensor_writers {
+                let reading = self.dobot.get_color_sensor();
+                let availability = SensorState {
+                    is_on: reading.is_ok(),
+                };
+                availability_writer.write(&availability, None).unwrap();
+                match reading {
+                    Ok(reading) => color_writer.write(&sensor_color(&reading), None).unwrap(),
+                    Err(error) => eprintln!("\nFailed to read colour sensor: {}", error),
+                }
+            }
+
+            if let Some((port, availability_writer, presence_writer)) = &topics.ir_switch_writers {
+                let reading = self.dobot.get_ir_switch(*port);
+                let availability = SensorState {
+                    is_on: reading.is_ok(),
+                };
+                availability_writer.write(&availability, None).unwrap();
+                match reading {
+                    Ok(present) => presence_writer.write(&Presence { present }, None).unwrap(),
+                    Err(error) => eprintln!("\nFailed to read IR switch: {}", error),
+                }
+            }
+        }
+    }
+
+    fn home(&mut self, reader: &DataReader<HomeDobot>) {
+        if let Ok(sample_data) = reader.read(
+            1,
+            &[SampleStateKind::NotRead],
+            ANY_VIEW_STATE,
+            ANY_INSTANCE_STATE,
+        ) {
+            for sample in sample_data {
+                if let Ok(HomeDobot { home: true }) = sample.data() {
+                    self.request_homing();
+                }
+            }
+        }
+    }
+
+    fn request_homing(&mut self) {
+        match start_homing(&mut self.dobot) {
+            Ok((index, started)) => {
+                self.homing = Some((index, started));
+                self.last_move_index = Some(index);
+                self.homing_state = HomingState::Homing;
+            }
+            Err(error) => {
+                eprintln!("\nFailed to home arm: {}", error);
+                self.homing_state = HomingState::Failed;
+            }
+        }
+    }
+
+    fn control_program(&mut self, topics: &ProgramTopics) {
+        if let Ok(sample_data) = topics.program_command_reader.read(
+            1,
+            &[SampleStateKind::NotRead],
+            ANY_VIEW_STATE,
+            ANY_INSTANCE_STATE,
+        ) {
+            for sample in sample_data {
+                if let Ok(command) = sample.data() {
+                    let action = match command.action() {
+                        Ok(action) => action,
+                        Err(action) => {
+                            eprintln!("\nUnknown program action {}", action);
+                            continue;
+                        }
+                    };
+                    match action {
+                        ProgramAction::Play if self.player.is_some() => {
+                            eprintln!("\nA program is playing already")
+                        }
+                        ProgramAction::Play => match Program::load(&self.config.program) {
+                            Ok(program) => self.player = Some(Player::new(program)),
+                            Err(error) => eprintln!("\nFailed to load program: {}", error),
+                        },
+                        ProgramAction::Stop => {
+                            if self.player.take().is_some() {
+                                let stopped = self
+                                    .dobot
+                                    .abort_queued_cmds()
+                                    .and_then(|()| self.dobot.set_e_motor(belt_motor(0)));
+                                match stopped {
+                                    Ok(()) => self.last_move_index = None,
+                                    Err(error) => eprintln!("\nFailed to stop program: {}", error),
+                                }
+                            }
+                        }
+                        ProgramAction::RecordPose => {
+                            record_pose(&mut self.dobot, &self.config.program)
+                        }
+                        ProgramAction::RecordWait => {
+                            let wait = Duration::from_millis(command.wait_ms.into());
+                            record_step(&self.config.program, &Step::Wait(wait));
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(playing) = &mut self.player {
+            match playing.poll(&mut self.dobot) {
+                Ok(true) => self.player = None,
+                Ok(false) => self.last_move_index = playing.last_index().or(self.last_move_index),
+                Err(error) => eprintln!("\nFailed to play program: {}", error),
+            }
+        }
+        let program_state = DobotProgramState {
+            is_playing: self.player.is_some(),
+            step: self
+                .player
+                .as_ref()
+                .map_or(0, |playing| playing.steps_done() as u32),
+            step_count: self
+                .player
+                .as_ref()
+                .map_or(0, |playing| playing.step_count() as u32),
+        };
+        topics
+            .program_state_writer
+            .write(&program_state, None)
+            .unwrap();
+    }
+
+    fn control_queue(&mut self, reader: &DataReader<DobotQueueControl>) {
+        if let Ok(sample_data) = reader.read(
+            1,
+            &[SampleStateKind::NotRead],
+            ANY_VIEW_STATE,
+            ANY_INSTANCE_STATE,
+        ) {
+            for sample in sample_data {
+                if let Ok(queue_control) = sample.data() {
+                    let action = match queue_control.action() {
+                        Ok(action) => action,
+                        Err(action) => {
+                            eprintln!("\nUnknown queue action {}", action);
+                            continue;
+                        }
+                    };
+                    let result = match action {
+                        QueueAction::Pause => self.dobot.set_queued_cmd_stop_exec(),
+                        QueueAction::Resume => self.dobot.set_queued_cmd_start_exec(),
+                        QueueAction::Abort => self.abort_queue(),
+                    };
+                    if let Err(error) = result {
+                        eprintln!("\nFailed to control queue: {}", error);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Stop the arm and drop the queued moves.
+    fn abort_queue(&mut self) -> Result<(), dobot::error::Error> {
+        self.dobot.abort_queued_cmds()?;
+        self.last_move_index = None;
+        // The arm is not calibrated when homing is cut short.
+        if self.homing.take().is_some() {
+            self.homing_state = HomingState::Failed;
+        }
+        Ok(())
+    }
+
+    fn publish_homing(&mut self, writer: &DataWriter<DobotHoming>) {
+        self.check_homing();
+        let dobot_homing = DobotHoming {
+            state: self.homing_state as u8,
+            elapsed_ms: self
+                .homing
+                .map_or(0, |(_, started)| started.elapsed().as_millis() as u32),
+        };
+        writer.write(&dobot_homing, None).unwrap();
+    }
+
+    /// Record the arm as homed once it is done with the homing command.
+    fn check_homing(&mut self) {
+        if let Some((index, _)) = self.homing {
+            match self.dobot.is_queued_cmd_done(index) {
+                Ok(true) => {
+                    self.homing = None;
+                    self.homing_state = HomingState::Homed;
+                    let saved = home_record(&mut self.dobot)
+                        .and_then(|record| Ok(record.save(&self.config.home_record)?));
+                    if let Err(error) = saved {
+                        eprintln!("\nFailed to record homing: {}", error);
+                    }
+                }
+                Ok(false) => (),
+                Err(error) => eprintln!("\nFailed to read queue index: {}", error),
+            }
+        }
+    }
+
+    fn clear_alarms(&mut self, reader: &DataReader<ClearAlarms>) {
+        if let Ok(sample_data) = reader.read(
+            1,
+            &[SampleStateKind::NotRead],
+            ANY_VIEW_STATE,
+            ANY_INSTANCE_STATE,
+        ) {
+            for sample in sample_data {
+                if let Ok(ClearAlarms { clear: true }) = sample.data() {
+                    match self.dobot.clear_alarms() {
+                        // Read the alarms again to publish the result right away.
+                        Ok(()) => self.last_alarm_check = None,
+                        Err(error) => eprintln!("\nFailed to clear alarms: {}", error),
+                    }
+                }
+            }
+        }
+    }
+
+    fn publish_alarms(&mut self, topics: &AlarmTopics) {
+        if self
+            .last_alarm_check
+            .is_none_or(|time| time.elapsed() >= ALARM_PERIOD)
+        {
+            self.last_alarm_check = Some(Instant::now());
+            match self.dobot.get_alarms() {
+                Ok(current_alarms) => {
+                    if current_alarms != self.alarms {
+                        eprintln!("\nALARMS: {}", show_alarms(&current_alarms));
+                        for axis in lost_step_axes(&self.alarms, &current_alarms) {
+                            topics
+                                .lost_steps_writer
+                                .write(&DobotLostSteps { axis }, None)
+                                .unwrap();
+                        }
+                        self.alarms = current_alarms;
+                    }
+                    let dobot_alarms = DobotAlarms {
+                        codes: self.alarms.codes().collect(),
+                    };
+                    topics.alarms_writer.write(&dobot_alarms, None).unwrap();
+                }
+                Err(error) => eprintln!("\nFailed to read alarms: {}", error),
+            }
+        }
+    }
+
+    fn publish_housekeeping(&mut self, writer: &DataWriter<DobotHousekeeping>) {
+        if self
+            .last_housekeeping
+            .is_none_or(|time| time.elapsed() >= HOUSEKEEPING_PERIOD)
+        {
+            self.last_housekeeping = Some(Instant::now());
+            match read_housekeeping(&mut self.dobot) {
+                Ok(current) => {
+                    if current.serial_number != self.housekeeping.serial_number
+                        || current.firmware_version != self.housekeeping.firmware_version
+                    {
+                        eprintln!(
+                            "\nDOBOT: {} serial number {} firmware {}",
+                            current.name, current.serial_number, current.firmware_version
+                        );
+                    }
+                    self.housekeeping = current;
                 }
+                Err(error) => {
+                    eprintln!("\nFailed to read housekeeping: {}", error);
+                    self.housekeeping.is_available = false;
+                }
+            }
+            writer.write(&self.housekeeping, None).unwrap();
+        }
+    }
+
+    fn publish_pose(&mut self, writer: &DataWriter<DobotPose>) {
+        match read_pose(&mut self.dobot) {
+            Ok(dobot_pose) => {
+                writer.write(&dobot_pose, None).unwrap();
+                print!("POSE: {:<50}", show_dobot_pose(&dobot_pose));
             }
+            Err(error) => print!("POSE: {:<50}", error),
         }
+    }
 
-        let pose = dobot.get_pose().unwrap();
-        let dobot_pose = DobotPose {
-            x: pose.x,
-            y: pose.y,
-            z: pose.z,
-            r: pose.r,
+    fn publish_end_effector(&self, topics: &EndEffectorTopics) {
+        topics
+            .suction_writer
+            .write(&self.suction_state, None)
+            .unwrap();
+        topics
+            .end_effector_writer
+            .write(&self.end_effector_state, None)
+            .unwrap();
+    }
+
+    fn publish_motion_state(&mut self, writer: &DataWriter<DobotMotionState>) {
+        // Only the last move matters, the queue executes moves in order.
+        if let Some(index) = self.last_move_index {
+            match self.dobot.is_queued_cmd_done(index) {
+                Ok(true) => self.last_move_index = None,
+                Ok(false) => (),
+                Err(error) => eprintln!("\nFailed to read queue index: {}", error),
+            }
+        }
+        let motion_state = DobotMotionState {
+            is_moving: self.last_move_index.is_some(),
         };
+        writer.write(&motion_state, None).unwrap();
+    }
+}
+
+fn run<T: Transport>(dobot: Dobot<T>, config: &Config) -> Result<(), dobot::error::Error> {
+    let dds = Dds::new(config.domain_id);
+    let topics = Topics::new(&dds, config);
+    let mut node = Node::new(dobot, config)?;
+
+    loop {
+        let start = Instant::now();
 
-        pose_writer.write(&dobot_pose, None).unwrap();
-        suction_writer.write(&suction_state, None).unwrap();
+        node.poll(&topics);
 
-        print!("POSE: {:<50}", show_dobot_pose(&dobot_pose));
         if let Some(time_remaining) = LOOP_PERIOD.checked_sub(start.elapsed()) {
             std::thread::sleep(time_remaining);
             print!("  REMAINING TIME: {:?}", time_remaining)
@@ -241,3 +1395,164 @@ fn main() -> Result<(), dobot::error::Error> {
         std::io::stdout().flush().unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dobot::{emulator::Emulator, transport::PipeEnd};
+
+    fn emulated() -> Dobot<PipeEnd> {
+        Dobot::builder()
+            .poll_interval(Duration::from_millis(5))
+            .connect(Emulator::new().spawn())
+            .unwrap()
+    }
+
+    /// Switch an end effector and wait until the arm got to it in its queue.
+    fn switch(dobot: &mut Dobot<PipeEnd>, command: EndEffectorCommand) {
+        let tool = command.tool().unwrap();
+        switch_end_effector(dobot, tool, &command).unwrap();
+        dobot
+            .set_wait_cmd(Duration::ZERO)
+            .unwrap()
+            .wait_timeout(Duration::from_secs(2))
+            .unwrap();
+    }
+
+    #[test]
+    fn switches_the_selected_end_effector() {
+        let mut dobot = emulated();
+        switch(
+            &mut dobot,
+            EndEffectorCommand::new(EndEffectorType::Gripper, true),
+        );
+        let gripper = dobot.get_end_effector_gripper().unwrap();
+        assert!(gripper.is_ctrl_enabled && gripper.is_gripped);
+        assert!(!dobot.get_end_effector_suction_cup().unwrap().is_sucked);
+        assert!(!dobot.get_end_effector_laser().unwrap().is_on);
+
+        switch(
+            &mut dobot,
+            EndEffectorCommand::new(EndEffectorType::Laser, true),
+        );
+        let laser = dobot.get_end_effector_laser().unwrap();
+        assert!(laser.is_ctrl_enabled && laser.is_on);
+
+        switch(
+            &mut dobot,
+            EndEffectorCommand::new(EndEffectorType::SuctionCup, true),
+        );
+        assert!(dobot.get_end_effector_suction_cup().unwrap().is_sucked);
+        assert!(dobot.get_end_effector_gripper().unwrap().is_gripped);
+    }
+
+    #[test]
+    fn disables_the_selected_end_effector() {
+        let mut dobot = emulated();
+        switch(
+            &mut dobot,
+            EndEffectorCommand::new(EndEffectorType::Gripper, true),
+        );
+        switch(
+            &mut dobot,
+            EndEffectorCommand {
+                is_enabled: false,
+                ..EndEffectorCommand::new(EndEffectorType::Gripper, false)
+            },
+        );
+        let gripper = dobot.get_end_effector_gripper().unwrap();
+        assert!(!gripper.is_ctrl_enabled && !gripper.is_gripped);
+    }
+
+    #[test]
+    fn publishes_the_detected_colour_channel() {
+        let reading = ColorSensorReading {
+            red: 0,
+            green: 1,
+            blue: 0,
+        };
+        assert_eq!(
+            sensor_color(&reading),
+            Color {
+                red: 0,
+                green: 255,
+                blue: 0,
+            }
+        );
+
+        let nothing = ColorSensorReading {
+            red: 0,
+            green: 0,
+            blue: 0,
+        };
+        assert_eq!(
+            sensor_color(&nothing),
+            Color {
+                red: 0,
+                green: 0,
+                blue: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn reads_the_identity_of_the_arm() {
+        let mut dobot = emulated();
+        dobot.set_device_name("sorting cell").unwrap();
+        let housekeeping = read_housekeeping(&mut dobot).unwrap();
+        assert!(housekeeping.is_available);
+        assert_eq!(housekeeping.serial_number, "EMULATOR");
+        assert_eq!(housekeeping.name, "sorting cell");
+        assert_eq!(housekeeping.firmware_version, "3.7.0");
+        assert!(housekeeping.uptime_ms < 60_000);
+    }
+
+    #[test]
+    fn homes_again_after_losing_steps() {
+        let mut emulator = Emulator::new();
+        emulator.lose_steps(1);
+        let dobot = Dobot::builder()
+            .poll_interval(Duration::from_millis(5))
+            .connect(emulator.spawn())
+            .unwrap();
+        let home_record = std::env::temp_dir().join("dobot-home-lost-steps-test");
+        let config = Config::from_args(
+            [
+                "--emulator",
+                "--home",
+                "never",
+                "--lost-step-threshold",
+                "5",
+                "--home-record",
+                home_record.to_str().unwrap(),
+            ]
+            .map(String::from),
+        )
+        .unwrap();
+        let mut node = Node::new(dobot, &config).unwrap();
+
+        let pose = node.dobot.get_pose().unwrap();
+        node.dobot
+            .set_ptp_cmd(pose.x, pose.y, pose.z, pose.r, Mode::MODE_PTP_MOVJ_XYZ)
+            .unwrap()
+            .wait_timeout(Duration::from_secs(2))
+            .unwrap();
+        let alarms = node.dobot.get_alarms().unwrap();
+        assert!(alarms.contains(Alarm::LostStepAxis2));
+        assert_eq!(lost_step_axes(&Alarms::default(), &alarms), [1]);
+        assert!(lost_step_axes(&alarms, &alarms).is_empty());
+
+        node.abort_queue().unwrap();
+        node.dobot.clear_alarms().unwrap();
+        node.request_homing();
+        assert_eq!(node.homing_state, HomingState::Homing);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while node.homing_state == HomingState::Homing && Instant::now() < deadline {
+            node.check_homing();
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(node.homing_state, HomingState::Homed);
+        assert!(node.dobot.get_alarms().unwrap().is_empty());
+        std::fs::remove_file(home_record).unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+//! Moves of the arm and the conveyor belt, shared by the DDS handlers and the
+//! program player.
+
+use crate::dobot::{
+    base::Dobot,
+    command::{CartesianPoint, EMotor, Mode},
+    error::Result as DobotResult,
+    transport::Transport,
+};
+use types::{DobotPose, MoveType};
+
+const MIN_BELT_SPEED: i32 = 500;
+const MAX_BELT_SPEED: i32 = 15000;
+
+/// The PTP mode of a move type, `None` for arcs which are not PTP moves.
+pub fn ptp_mode(move_type: MoveType) -> Option<Mode> {
+    let mode = match move_type {
+        MoveType::JumpXyz => Mode::MODE_PTP_JUMP_XYZ,
+        MoveType::MoveJXyz => Mode::MODE_PTP_MOVJ_XYZ,
+        MoveType::MoveLXyz => Mode::MODE_PTP_MOVL_XYZ,
+        MoveType::JumpAngle => Mode::MODE_PTP_JUMP_ANGLE,
+        MoveType::MoveJAngle => Mode::MODE_PTP_MOVJ_ANGLE,
+        MoveType::MoveLAngle => Mode::MODE_PTP_MOVL_ANGLE,
+        MoveType::MoveJAngleIncrement => Mode::MODE_PTP_MOVJ_INC,
+        MoveType::MoveLXyzIncrement => Mode::MODE_PTP_MOVL_INC,
+        MoveType::MoveJXyzIncrement => Mode::MODE_PTP_MOVJ_XYZ_INC,
+        MoveType::JumpMoveLXyz => Mode::MODE_PTP_JUMP_MOVL_XYZ,
+        MoveType::Arc => return None,
+    };
+    Some(mode)
+}
+
+/// Queue a PTP move, which includes the rail if there is one, and return its queue index.
+pub fn move_arm<T: Transport>(
+    dobot: &mut Dobot<T>,
+    mode: Mode,
+    pose: &DobotPose,
+) -> DobotResult<u64> {
+    let handle = match dobot.is_with_l() {
+        true => dobot.set_ptp_with_l_cmd(pose.x, pose.y, pose.z, pose.r, pose.l, mode)?,
+        false => dobot.set_ptp_cmd(pose.x, pose.y, pose.z, pose.r, mode)?,
+    };
+    Ok(handle.index())
+}
+
+pub fn cartesian_point(pose: &DobotPose) -> CartesianPoint {
+    CartesianPoint {
+        x: pose.x,
+        y: pose.y,
+        z: pose.z,
+        r: pose.r,
+    }
+}
+
+pub fn belt_motor(speed: i32) -> EMotor {
+    let speed = match speed {
+        0 => 0,
+        s => s.clamp(MIN_BELT_SPEED, MAX_BELT_SPEED),
+    };
+    EMotor {
+        index: 0,
+        is_enabled: true,
+        speed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_types_share_their_values_with_the_ptp_modes() {
+        for value in 0..10 {
+            let move_type = MoveType::try_from(value).unwrap();
+            assert_eq!(ptp_mode(move_type).map(|mode| mode as u8), Some(value));
+        }
+        assert_eq!(
+            ptp_mode(MoveType::MoveJXyzIncrement),
+            Some(Mode::MODE_PTP_MOVJ_XYZ_INC)
+        );
+    }
+
+    #[test]
+    fn arcs_have_no_ptp_mode() {
+        assert_eq!(ptp_mode(MoveType::Arc), None);
+    }
+}
@@ -0,0 +1,94 @@
+//! Parameters of the motion profiles the node switches between.
+//!
+//! The joint and coordinate velocities stay at the defaults the node connects
+//! the driver with, the profiles scale them down.
+
+use crate::dobot::{
+    base::{Dobot, DEFAULT_PTP_COORDINATE_PARAMS, DEFAULT_PTP_JUMP_PARAMS},
+    command::{ArcParams, PtpCommonParams, PtpJumpParams},
+    error::Result as DobotResult,
+    transport::Transport,
+};
+use types::MotionProfile;
+
+/// Jump height of the carry profile, the default leaves little room for a carried block.
+const CARRY_JUMP_HEIGHT: f32 = 20.0;
+
+/// PTP and arc parameters of a motion profile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfileParams {
+    pub jump: PtpJumpParams,
+    pub common: PtpCommonParams,
+    pub arc: ArcParams,
+}
+
+impl ProfileParams {
+    pub fn of(profile: MotionProfile) -> Self {
+        let default_jump = DEFAULT_PTP_JUMP_PARAMS;
+        let (jump_height, velocity_ratio, acceleration_ratio) = match profile {
+            MotionProfile::Gentle => (default_jump.jump_height, 30.0, 30.0),
+            MotionProfile::Fast => (default_jump.jump_height, 100.0, 100.0),
+            // Accelerating gently matters more than moving slowly to keep hold of a block.
+            MotionProfile::Carry => (CARRY_JUMP_HEIGHT.max(default_jump.jump_height), 60.0, 25.0),
+        };
+        // Arc moves ignore the PTP ratios, so they are scaled here.
+        let coordinate = DEFAULT_PTP_COORDINATE_PARAMS;
+        let velocity = |velocity: f32| velocity * velocity_ratio / 100.0;
+        let acceleration = |acceleration: f32| acceleration * acceleration_ratio / 100.0;
+        Self {
+            jump: PtpJumpParams {
+                jump_height,
+                z_limit: default_jump.z_limit,
+            },
+            common: PtpCommonParams {
+                velocity_ratio,
+                acceleration_ratio,
+            },
+            arc: ArcParams {
+                xyz_velocity: velocity(coordinate.xyz_velocity),
+                r_velocity: velocity(coordinate.r_velocity),
+                xyz_acceleration: acceleration(coordinate.xyz_acceleration),
+                r_acceleration: acceleration(coordinate.r_acceleration),
+            },
+        }
+    }
+
+    /// Queue the parameters, so that they apply to the moves queued afterwards.
+    pub fn queue<T: Transport>(&self, dobot: &mut Dobot<T>) -> DobotResult<()> {
+        dobot.set_ptp_jump_params(self.jump)?;
+        dobot.set_ptp_common_params(self.common)?;
+        dobot.set_arc_params(self.arc)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dobot::emulator::Emulator;
+    use std::time::Duration;
+
+    #[test]
+    fn reads_back_the_queued_profile() {
+        let mut dobot = Dobot::builder()
+            .poll_interval(Duration::from_millis(5))
+            .connect(Emulator::new().spawn())
+            .unwrap();
+        for profile in [
+            MotionProfile::Gentle,
+            MotionProfile::Carry,
+            MotionProfile::Fast,
+        ] {
+            let params = ProfileParams::of(profile);
+            params.queue(&mut dobot).unwrap();
+            dobot
+                .set_wait_cmd(Duration::ZERO)
+                .unwrap()
+                .wait_timeout(Duration::from_secs(1))
+                .unwrap();
+            assert_eq!(dobot.get_ptp_jump_params().unwrap(), params.jump);
+            assert_eq!(dobot.get_ptp_common_params().unwrap(), params.common);
+            assert_eq!(dobot.get_arc_params().unwrap(), params.arc);
+        }
+    }
+}
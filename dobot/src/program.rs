@@ -0,0 +1,368 @@
+//! Programs of moves, suction, waits and belt speeds, recorded to a file and
+//! played back on the queue of the arm.
+//!
+//! A program file holds one step per line, lines starting with `#` are comments:
+//!
+//! ```text
+//! move jump 251 -123 0 0
+//! suction on
+//! wait 200
+//! move movl 251 -123 25 0
+//! belt 7500
+//! ```
+//!
+//! Moves are `jump`, `movj` or `movl` to X, Y, Z and R, followed by the rail
+//! position L if there is a rail.
+
+use crate::{
+    dobot::{
+        base::Dobot,
+        command::{Mode, SetEMotor},
+        error::Error as DobotError,
+        transport::Transport,
+    },
+    motion::{belt_motor, move_arm},
+};
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::{self, OpenOptions},
+    io::{Error as IoError, ErrorKind, Write},
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
+use types::DobotPose;
+
+/// Steps queued on the arm ahead of the one it executes.
+const QUEUE_AHEAD: usize = 8;
+
+/// Moves a program can make, to cartesian coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepMove {
+    Jump,
+    MovJ,
+    MovL,
+}
+
+impl StepMove {
+    fn name(self) -> &'static str {
+        match self {
+            StepMove::Jump => "jump",
+            StepMove::MovJ => "movj",
+            StepMove::MovL => "movl",
+        }
+    }
+
+    fn mode(self) -> Mode {
+        match self {
+            StepMove::Jump => Mode::MODE_PTP_JUMP_XYZ,
+            StepMove::MovJ => Mode::MODE_PTP_MOVJ_XYZ,
+            StepMove::MovL => Mode::MODE_PTP_MOVL_XYZ,
+        }
+    }
+}
+
+/// A step of a program.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    Move {
+        move_type: StepMove,
+        pose: DobotPose,
+    },
+    Suction(bool),
+    Wait(Duration),
+    /// Speed of the conveyor belt, 0 to stop it.
+    Belt(i32),
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Move { move_type, pose } => write!(
+                f,
+                "move {} {} {} {} {} {}",
+                move_type.name(),
+                pose.x,
+                pose.y,
+                pose.z,
+                pose.r,
+                pose.l
+            ),
+            Step::Suction(true) => write!(f, "suction on"),
+            Step::Suction(false) => write!(f, "suction off"),
+            Step::Wait(duration) => write!(f, "wait {}", duration.as_millis()),
+            Step::Belt(speed) => write!(f, "belt {}", speed),
+        }
+    }
+}
+
+impl FromStr for Step {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["move", name, coordinates @ ..] => {
+                let move_type = match *name {
+                    "jump" => StepMove::Jump,
+                    "movj" => StepMove::MovJ,
+                    "movl" => StepMove::MovL,
+                    name => return Err(format!("unknown move {}", name)),
+                };
+                let coordinates = coordinates
+                    .iter()
+                    .map(|coordinate| parse(coordinate))
+                    .collect::<Result<Vec<f32>, _>>()?;
+                let (x, y, z, r, l) = match *coordinates.as_slice() {
+                    [x, y, z, r] => (x, y, z, r, 0.0),
+                    [x, y, z, r, l] => (x, y, z, r, l),
+                    _ => return Err("expected X Y Z R [L]".to_string()),
+                };
+                let pose = DobotPose { x, y, z, r, l };
+                Ok(Step::Move { move_type, pose })
+            }
+            ["suction", "on"] => Ok(Step::Suction(true)),
+            ["suction", "off"] => Ok(Step::Suction(false)),
+            ["wait", milliseconds] => Ok(Step::Wait(Duration::from_millis(parse(milliseconds)?))),
+            ["belt", speed] => Ok(Step::Belt(parse(speed)?)),
+            _ => Err(format!("unknown step {}", line)),
+        }
+    }
+}
+
+fn parse<T: FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {}", value))
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Program {
+    pub steps: Vec<Step>,
+}
+
+impl Program {
+    pub fn load(path: &Path) -> Result<Self, IoError> {
+        let content = fs::read_to_string(path)?;
+        let mut steps = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let step = line.parse().map_err(|message| {
+                IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("line {}: {}", number + 1, message),
+                )
+            })?;
+            steps.push(step);
+        }
+        Ok(Self { steps })
+    }
+
+    /// Add a step to the end of a program file, which is created if needed.
+    pub fn append(path: &Path, step: &Step) -> Result<(), IoError> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", step)
+    }
+}
+
+/// Queue a step and return its queue index.
+fn queue_step<T: Transport>(dobot: &mut Dobot<T>, step: &Step) -> Result<u64, DobotError> {
+    let handle = match *step {
+        Step::Move { move_type, pose } => return move_arm(dobot, move_type.mode(), &pose),
+        Step::Suction(is_on) => dobot.set_end_effector_suction_cup(is_on)?,
+        Step::Wait(duration) => dobot.set_wait_cmd(duration)?,
+        // Queued rather than set right away, so that the belt starts in sequence.
+        Step::Belt(speed) => dobot.queue(SetEMotor(belt_motor(speed)))?,
+    };
+    Ok(handle.index())
+}
+
+/// Plays a program by feeding its steps to the queue of the arm.
+#[derive(Debug, Clone)]
+pub struct Player {
+    steps: Vec<Step>,
+    next: usize,
+    /// Queue indices of the steps queued but not executed yet.
+    queued: VecDeque<u64>,
+}
+
+impl Player {
+    pub fn new(program: Program) -> Self {
+        Self {
+            steps: program.steps,
+            next: 0,
+            queued: VecDeque::new(),
+        }
+    }
+
+    /// Queue the next steps as the arm executes the previous ones, and return
+    /// whether the program is done.
+    ///
+    /// Only a few steps are queued at a time, so that long programs do not
+    /// overflow the queue of the arm.
+    pub fn poll<T: Transport>(&mut self, dobot: &mut Dobot<T>) -> Result<bool, DobotError> {
+        if !self.queued.is_empty() {
+            let current_index = dobot.get_queued_cmd_current_index()?;
+            while self
+                .queued
+                .front()
+                .is_some_and(|&index| index <= current_index)
+            {
+                self.queued.pop_front();
+            }
+        }
+        while self.queued.len() < QUEUE_AHEAD {
+            let Some(step) = self.steps.get(self.next) else {
+                break;
+            };
+            self.queued.push_back(queue_step(dobot, step)?);
+            self.next += 1;
+        }
+        Ok(self.is_done())
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next == self.steps.len() && self.queued.is_empty()
+    }
+
+    /// Number of steps executed so far.
+    pub fn steps_done(&self) -> usize {
+        self.next - self.queued.len()
+    }
+
+    pub fn step_count(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Queue index of the last step queued.
+    pub fn last_index(&self) -> Option<u64> {
+        self.queued.back().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dobot::emulator::Emulator;
+    use std::{path::PathBuf, thread, time::Instant};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("dobot-program-{}-{}", name, std::process::id()))
+    }
+
+    fn move_step(move_type: StepMove, x: f32, y: f32, z: f32, r: f32, l: f32) -> Step {
+        let pose = DobotPose { x, y, z, r, l };
+        Step::Move { move_type, pose }
+    }
+
+    #[test]
+    fn round_trips_steps() {
+        let steps = [
+            move_step(StepMove::Jump, 251.0, -123.5, 0.0, 0.0, 0.0),
+            move_step(StepMove::MovJ, 0.25, 200.0, -41.3, 90.0, 0.0),
+            move_step(StepMove::MovL, 251.0, -123.0, 25.0, -45.0, 312.5),
+            Step::Suction(true),
+            Step::Suction(false),
+            Step::Wait(Duration::from_millis(200)),
+            Step::Belt(7500),
+            Step::Belt(-500),
+            Step::Belt(0),
+        ];
+        for step in steps {
+            let line = step.to_string();
+            assert_eq!(line.parse::<Step>(), Ok(step), "{}", line);
+        }
+    }
+
+    #[test]
+    fn formats_steps_as_documented() {
+        let step = move_step(StepMove::Jump, 251.0, -123.0, 0.0, 0.0, 0.0);
+        assert_eq!(step.to_string(), "move jump 251 -123 0 0 0");
+        assert_eq!(Step::Suction(true).to_string(), "suction on");
+        assert_eq!(
+            Step::Wait(Duration::from_millis(200)).to_string(),
+            "wait 200"
+        );
+        assert_eq!(Step::Belt(7500).to_string(), "belt 7500");
+    }
+
+    #[test]
+    fn parses_moves_without_a_rail() {
+        assert_eq!(
+            "move  movl 251 -123 25 0".parse::<Step>(),
+            Ok(move_step(StepMove::MovL, 251.0, -123.0, 25.0, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_steps() {
+        for line in [
+            "move arc 1 2 3 4",
+            "move jump 1 2 3",
+            "move jump 1 2 3 4 5 6",
+            "move jump 1 2 three 4",
+            "suction maybe",
+            "wait -1",
+            "wait 1.5",
+            "belt fast",
+            "grip on",
+            "",
+        ] {
+            assert!(line.parse::<Step>().is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn appends_and_loads_steps() {
+        let path = temp_path("append");
+        let _ = fs::remove_file(&path);
+        let steps = [
+            move_step(StepMove::MovJ, 200.0, 0.0, 50.0, 0.0, 0.0),
+            Step::Suction(true),
+            Step::Wait(Duration::from_millis(500)),
+        ];
+        for step in &steps {
+            Program::append(&path, step).unwrap();
+        }
+        let loaded = Program::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().steps, steps);
+    }
+
+    #[test]
+    fn reports_the_invalid_line() {
+        let path = temp_path("invalid");
+        fs::write(&path, "# pick up\n\nsuction on\nsuction maybe\n").unwrap();
+        let loaded = Program::load(&path);
+        fs::remove_file(&path).unwrap();
+        let error = loaded.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("line 4: "), "{}", error);
+    }
+
+    #[test]
+    fn plays_more_steps_than_are_queued_ahead() {
+        let mut dobot = Dobot::builder().connect(Emulator::new().spawn()).unwrap();
+        let mut steps = vec![Step::Suction(true), Step::Belt(1000)];
+        steps.extend((0..QUEUE_AHEAD * 2).map(|_| Step::Wait(Duration::from_millis(5))));
+        steps.push(Step::Belt(0));
+        let mut player = Player::new(Program { steps });
+        let step_count = player.step_count();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut is_done = player.poll(&mut dobot).unwrap();
+        assert!(player.steps_done() < step_count);
+        while !is_done {
+            assert!(Instant::now() < deadline, "the program did not finish");
+            thread::sleep(Duration::from_millis(5));
+            is_done = player.poll(&mut dobot).unwrap();
+        }
+        assert!(player.is_done());
+        assert_eq!(player.steps_done(), step_count);
+        assert_eq!(player.last_index(), None);
+    }
+}
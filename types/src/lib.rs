@@ -1,5 +1,35 @@
 use dust_dds::topic_definition::type_support::DdsType;
 
+pub mod pose_library;
+
+/// Declares an enum sent as a `u8` field of a DDS type, together with its
+/// conversion from that field.
+macro_rules! dds_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident = $value:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant = $value,)*
+        }
+
+        impl TryFrom<u8> for $name {
+            type Error = u8;
+
+            fn try_from(value: u8) -> Result<Self, Self::Error> {
+                match value {
+                    $($value => Ok($name::$variant),)*
+                    value => Err(value),
+                }
+            }
+        }
+    };
+}
+
 // ----------------------------------------------------------------------------
 
 #[derive(Copy, Clone, PartialEq, Eq, DdsType, Debug)]
@@ -30,9 +60,372 @@ pub struct DobotPose {
     pub y: f32,
     pub z: f32,
     pub r: f32,
+    /// Position of the sliding rail, 0 without a rail.
+    pub l: f32,
 }
 
 #[derive(Clone, Copy, PartialEq, DdsType, Debug)]
 pub struct Suction {
     pub is_on: bool
 }
+
+dds_enum! {
+    /// Kind of motion requested by a `DobotMoveCommand`.
+    ///
+    /// The values up to `JumpMoveLXyz` match the PTP modes of the Dobot. In the
+    /// `*Angle` modes the coordinates of the command are the joint angles j1 to
+    /// j4, in the `*Increment` modes they are added to the current position.
+    /// `Arc` moves on a circular arc through the via point of the command.
+    pub enum MoveType {
+        JumpXyz = 0,
+        MoveJXyz = 1,
+        MoveLXyz = 2,
+        JumpAngle = 3,
+        MoveJAngle = 4,
+        MoveLAngle = 5,
+        MoveJAngleIncrement = 6,
+        MoveLXyzIncrement = 7,
+        MoveJXyzIncrement = 8,
+        JumpMoveLXyz = 9,
+        Arc = 10,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, DdsType, Debug)]
+pub struct DobotMoveCommand {
+    /// A `MoveType` value.
+    pub move_type: u8,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub r: f32,
+    /// Position of the sliding rail, ignored without a rail and by arcs.
+    pub l: f32,
+    /// Point the arc passes through, only used by `MoveType::Arc`.
+    pub via_x: f32,
+    pub via_y: f32,
+    pub via_z: f32,
+    pub via_r: f32,
+}
+
+impl DobotMoveCommand {
+    pub fn new(move_type: MoveType, pose: DobotPose) -> Self {
+        Self {
+            move_type: move_type as u8,
+            x: pose.x,
+            y: pose.y,
+            z: pose.z,
+            r: pose.r,
+            l: pose.l,
+            via_x: 0.0,
+            via_y: 0.0,
+            via_z: 0.0,
+            via_r: 0.0,
+        }
+    }
+
+    /// Move on the arc from the current position through `via` to `pose`.
+    pub fn arc(via: DobotPose, pose: DobotPose) -> Self {
+        Self {
+            via_x: via.x,
+            via_y: via.y,
+            via_z: via.z,
+            via_r: via.r,
+            ..Self::new(MoveType::Arc, pose)
+        }
+    }
+
+    pub fn pose(&self) -> DobotPose {
+        DobotPose {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+            r: self.r,
+            l: self.l,
+        }
+    }
+
+    pub fn via(&self) -> DobotPose {
+        DobotPose {
+            x: self.via_x,
+            y: self.via_y,
+            z: self.via_z,
+            r: self.via_r,
+            l: self.l,
+        }
+    }
+
+    pub fn move_type(&self) -> Result<MoveType, u8> {
+        MoveType::try_from(self.move_type)
+    }
+}
+
+/// Alarms currently raised by the Dobot, as alarm codes.
+#[derive(Clone, PartialEq, Eq, DdsType, Debug)]
+pub struct DobotAlarms {
+    pub codes: Vec<u8>,
+}
+
+/// Request to clear the alarms of the Dobot.
+#[derive(Clone, Copy, PartialEq, Eq, DdsType, Debug)]
+pub struct ClearAlarms {
+    pub clear: bool,
+}
+
+/// Whether the Dobot is still executing the moves it was sent.
+#[derive(Clone, Copy, PartialEq, Eq, DdsType, Debug)]
+pub struct DobotMotionState {
+    pub is_moving: bool,
+}
+
+/// Moves one axis of the Dobot continuously, for manual positioning.
+///
+/// The axis keeps moving until a command with direction 0 is received.
+#[derive(Clone, Copy, PartialEq, DdsType, Debug)]
+pub struct DobotJogCommand {
+    /// Move the joints j1 to j4 instead of the x, y, z and r coordinates.
+    pub is_joint: bool,
+    /// Index 0 to 3 of the joint or coordinate, 4 for the sliding rail.
+    pub axis: u8,
+    /// Positive or negative to move in that direction, 0 to stop.
+    pub direction: i8,
+    /// Percentage of the configured jog velocity.
+    pub speed_ratio: f32,
+}
+
+dds_enum! {
+    /// Tool mounted on the Dobot, selects the end effector an `EndEffectorCommand` is for.
+    pub enum EndEffectorType {
+        SuctionCup = 0,
+        Gripper = 1,
+        Laser = 2,
+    }
+}
+
+/// Switches an end effector of the Dobot.
+///
+/// Active means sucking for the suction cup, closed for the gripper and
+/// firing for the laser. Disabling the tool turns the pump of the suction
+/// cup and the gripper off.
+#[derive(Clone, Copy, PartialEq, Eq, DdsType, Debug)]
+pub struct EndEffectorCommand {
+    /// An `EndEffectorType` value.
+    pub tool: u8,
+    pub is_enabled: bool,
+    pub is_active: bool,
+}
+
+impl EndEffectorCommand {
+    pub fn new(tool: EndEffectorType, is_active: bool) -> Self {
+        Self {
+            tool: tool as u8,
+            is_enabled: true,
+            is_active,
+        }
+    }
+
+    pub fn tool(&self) -> Result<EndEffectorType, u8> {
+        EndEffectorType::try_from(self.tool)
+    }
+}
+
+/// State of the end effector last switched on the Dobot.
+#[derive(Clone, Copy, PartialEq, Eq, DdsType, Debug)]
+pub struct EndEffectorState {
+    /// An `EndEffectorType` value.
+    pub tool: u8,
+    pub is_enabled: bool,
+    pub is_active: bool,
+}
+
+/// Level of a digital pin of the extended I/O interface of the Dobot.
+#[derive(Clone, Copy, PartialEq, Eq, DdsType, Debug)]
+pub struct DobotIoLevel {
+    /// Pin address from 1 to 20.
+    pub address: u8,
+    pub level: bool,
+}
+
+/// PWM signal of a pin of the extended I/O interface of the Dobot.
+#[derive(Clone, Copy, PartialEq, DdsType, Debug)]
+pub struct DobotIoPwm {
+    pub address: u8,
+    /// Frequency in Hz, from 10 Hz to 1 MHz.
+    pub frequency: f32,
+    /// Duty cycle in percent.
+    pub duty_cycle: f32,
+}
+
+/// Reading of an analog pin of the extended I/O interface of the Dobot, from 0 to 4095.
+#[derive(Clone, Copy, PartialEq, Eq, DdsType, Debug)]
+pub struct DobotIoAnalog {
+    pub address: u8,
+    pub value: u16,
+}
+
+/// Identity and uptime of the Dobot, published periodically by the `dobot` node.
+#[derive(Clone, PartialEq, Eq, DdsType, Debug)]
+pub struct DobotHousekeeping {
+    /// Whether the arm answered, the identity is the last one read otherwise.
+    pub is_available: bool,
+    pub serial_number: String,
+    pub name: String,
+    pub firmware_version: String,
+    /// Milliseconds since the arm was powered on.
+    pub uptime_ms: u32,
+}
+
+dds_enum! {
+    /// Progress of homing the Dobot.
+    pub enum HomingState {
+        /// The arm was not homed by the `dobot` node.
+        Unknown = 0,
+        Homing = 1,
+        Homed = 2,
+        Failed = 3,
+    }
+}
+
+/// Homing state of the Dobot.
+#[derive(Clone, Copy, PartialEq, Eq, DdsType, Debug)]
+pub struct DobotHoming {
+    /// A `HomingState` value.
+    pub state: u8,
+    /// Milliseconds since homing started, 0 when the arm is not homing.
+    pub elapsed_ms: u32,
+}
+
+impl DobotHoming {
+    pub fn state(&self) -> Result<HomingState, u8> {
+        HomingState::try_from(self.state)
+    }
+}
+
+/// Request to home the Dobot.
+#[derive(Clone, Copy, PartialEq, Eq, DdsType, Debug)]
+pub struct HomeDobot {
+    pub home: bool,
+}
+
+/// Lost steps detected on an axis of the Dobot.
+///
+/// Published once per detection. The coordinates of the arm are not
+/// accurate anymore until it is homed again.
+#[derive(Clone, Copy, PartialEq, Eq, DdsType, Debug)]
+pub struct DobotLostSteps {
+    /// Index 0 to 3 of the joint.
+    pub axis: u8,
+}
+
+dds_enum! {
+    /// What to do with the commands queued on the Dobot.
+    pub enum QueueAction {
+        /// Stop once the current move is done, keeping the following moves.
+        Pause = 0,
+        Resume = 1,
+        /// Stop the arm right away and drop the queued moves.
+        Abort = 2,
+    }
+}
+
+/// Request to pause, resume or abort the moves of the Dobot.
+#[derive(Clone, Copy, PartialEq, Eq, DdsType, Debug)]
+pub struct DobotQueueControl {
+    /// A `QueueAction` value.
+    pub action: u8,
+}
+
+impl DobotQueueControl {
+    pub fn new(action: QueueAction) -> Self {
+        Self {
+            action: action as u8,
+        }
+    }
+
+    pub fn action(&self) -> Result<QueueAction, u8> {
+        QueueAction::try_from(self.action)
+    }
+}
+
+dds_enum! {
+    /// Velocity and acceleration the Dobot moves with.
+    pub enum MotionProfile {
+        /// Slow moves, such as close to people.
+        Gentle = 0,
+        /// The fastest moves, for an empty tool.
+        Fast = 1,
+        /// Smooth moves which do not shake off a carried block.
+        Carry = 2,
+    }
+}
+
+/// Request to switch the motion profile of the Dobot.
+///
+/// Moves requested before keep the profile they were requested with.
+#[derive(Clone, Copy, PartialEq, Eq, DdsType, Debug)]
+pub struct DobotMotionProfile {
+    /// A `MotionProfile` value.
+    pub profile: u8,
+}
+
+impl DobotMotionProfile {
+    pub fn new(profile: MotionProfile) -> Self {
+        Self {
+            profile: profile as u8,
+        }
+    }
+
+    pub fn profile(&self) -> Result<MotionProfile, u8> {
+        MotionProfile::try_from(self.profile)
+    }
+}
+
+dds_enum! {
+    /// What to do with the program of the `dobot` node.
+    pub enum ProgramAction {
+        Play = 0,
+        /// Stop the arm and the conveyor belt.
+        Stop = 1,
+        /// Append a move to the current pose of the arm.
+        RecordPose = 2,
+        /// Append a wait of `wait_ms`.
+        RecordWait = 3,
+    }
+}
+
+/// Request to play or record the program file of the `dobot` node.
+#[derive(Clone, Copy, PartialEq, Eq, DdsType, Debug)]
+pub struct DobotProgramCommand {
+    /// A `ProgramAction` value.
+    pub action: u8,
+    pub wait_ms: u32,
+}
+
+impl DobotProgramCommand {
+    pub fn new(action: ProgramAction) -> Self {
+        Self {
+            action: action as u8,
+            wait_ms: 0,
+        }
+    }
+
+    pub fn wait(wait_ms: u32) -> Self {
+        Self {
+            action: ProgramAction::RecordWait as u8,
+            wait_ms,
+        }
+    }
+
+    pub fn action(&self) -> Result<ProgramAction, u8> {
+        ProgramAction::try_from(self.action)
+    }
+}
+
+/// Progress of the program played by the `dobot` node.
+#[derive(Clone, Copy, PartialEq, Eq, DdsType, Debug)]
+pub struct DobotProgramState {
+    pub is_playing: bool,
+    /// Steps executed so far.
+    pub step: u32,
+    pub step_count: u32,
+}
@@ -0,0 +1,166 @@
+//! Named poses, taught with the `dobot` node and used by the nodes which move the arm.
+
+use crate::DobotPose;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Error as IoError, ErrorKind},
+    path::Path,
+};
+
+/// File of the pose library when no other one is given.
+pub const DEFAULT_POSE_LIBRARY: &str = "poses.txt";
+
+/// Poses by name, stored one per line as `name x y z r l`.
+///
+/// Lines starting with `#` are comments. Saving drops them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PoseLibrary {
+    poses: BTreeMap<String, DobotPose>,
+}
+
+impl PoseLibrary {
+    pub fn load(path: &Path) -> Result<Self, IoError> {
+        let content = fs::read_to_string(path)?;
+        let mut library = Self::default();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, pose) = parse_line(line).ok_or_else(|| {
+                IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid pose on line {}", number + 1),
+                )
+            })?;
+            library.insert(name, pose);
+        }
+        Ok(library)
+    }
+
+    /// Load a library, which is empty if the file does not exist yet.
+    pub fn load_or_default(path: &Path) -> Result<Self, IoError> {
+        match Self::load(path) {
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            result => result,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), IoError> {
+        let content: String = self
+            .poses
+            .iter()
+            .map(|(name, pose)| {
+                format!(
+                    "{} {} {} {} {} {}\n",
+                    name, pose.x, pose.y, pose.z, pose.r, pose.l
+                )
+            })
+            .collect();
+        fs::write(path, content)
+    }
+
+    pub fn get(&self, name: &str) -> Option<DobotPose> {
+        self.poses.get(name).copied()
+    }
+
+    /// Add a pose, replacing the one with the same name.
+    pub fn insert(&mut self, name: String, pose: DobotPose) {
+        self.poses.insert(name, pose);
+    }
+}
+
+fn parse_line(line: &str) -> Option<(String, DobotPose)> {
+    let mut fields = line.split_whitespace();
+    let name = fields.next()?.to_string();
+    let mut values = fields.map(|field| field.parse::<f32>().ok());
+    let mut value = || values.next().flatten();
+    let pose = DobotPose {
+        x: value()?,
+        y: value()?,
+        z: value()?,
+        r: value()?,
+        l: value()?,
+    };
+    match values.next() {
+        Some(_) => None,
+        None => Some((name, pose)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn pose(x: f32, y: f32, z: f32, r: f32, l: f32) -> DobotPose {
+        DobotPose { x, y, z, r, l }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("pose-library-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let mut library = PoseLibrary::default();
+        library.insert("pick".to_string(), pose(210.5, -12.25, 8.0, 0.0, 0.0));
+        library.insert("red".to_string(), pose(0.1, 230.0, -41.3, -90.0, 500.75));
+
+        let path = temp_path("round-trip");
+        library.save(&path).unwrap();
+        let loaded = PoseLibrary::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), library);
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let path = temp_path("comments");
+        fs::write(&path, "# taught poses\n\n  pick 1 2 3 4 0\n").unwrap();
+        let loaded = PoseLibrary::load(&path);
+        fs::remove_file(&path).unwrap();
+        let library = loaded.unwrap();
+        assert_eq!(library.get("pick"), Some(pose(1.0, 2.0, 3.0, 4.0, 0.0)));
+        assert_eq!(library.get("#"), None);
+    }
+
+    #[test]
+    fn reports_the_invalid_line() {
+        for content in [
+            "pick 1 2 3 4 0\nred 1 2 3 4\n",
+            "pick 1 2 3 4 0\nred 1 2 three 4 0\n",
+            "pick 1 2 3 4 0\nred 1 2 3 4 0 6\n",
+        ] {
+            let path = temp_path("invalid");
+            fs::write(&path, content).unwrap();
+            let loaded = PoseLibrary::load(&path);
+            fs::remove_file(&path).unwrap();
+            let error = loaded.unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+            assert_eq!(error.to_string(), "invalid pose on line 2");
+        }
+    }
+
+    #[test]
+    fn starts_empty_without_a_file() {
+        let path = temp_path("missing");
+        assert_eq!(
+            PoseLibrary::load_or_default(&path).unwrap(),
+            PoseLibrary::default()
+        );
+        assert_eq!(
+            PoseLibrary::load(&path).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn replaces_poses_with_the_same_name() {
+        let mut library = PoseLibrary::default();
+        library.insert("pick".to_string(), pose(1.0, 2.0, 3.0, 4.0, 0.0));
+        library.insert("pick".to_string(), pose(5.0, 6.0, 7.0, 8.0, 0.0));
+        assert_eq!(library.get("pick"), Some(pose(5.0, 6.0, 7.0, 8.0, 0.0)));
+    }
+}